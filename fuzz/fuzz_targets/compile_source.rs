@@ -0,0 +1,16 @@
+#![no_main]
+
+use bearbones::dialect::LanguageDialect;
+use bearbones::driver;
+use libfuzzer_sys::fuzz_target;
+
+// Any input must come back as artifacts or diagnostics; an internal error
+// means a pass panicked and is a bug.
+fuzz_target!(|data: &[u8]| {
+    if let Err(diags) = driver::compile_source(data, LanguageDialect::default()) {
+        assert!(!diags.is_empty());
+        if let Some(diag) = diags.iter().find(|d| d.internal) {
+            panic!("{}", diag.message);
+        }
+    }
+});
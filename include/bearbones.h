@@ -0,0 +1,45 @@
+/* C interface to the bearbones compiler, built with `--features ffi`.
+ *
+ * Strings and arrays returned by the library belong to it and stay valid
+ * until the next bb_compile or bb_run on the same thread. */
+#ifndef BEARBONES_H
+#define BEARBONES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define BB_ABI_VERSION 3
+
+#define BB_OK 0
+#define BB_COMPILE_ERROR 1
+#define BB_RUN_ERROR 2
+#define BB_INVALID_ARGUMENT 3
+
+#define BB_DIALECT_STRICT 0
+#define BB_DIALECT_EXTENDED 1
+
+#define BB_MEMORY_MANUAL 0
+#define BB_MEMORY_GC 1
+
+typedef struct bb_program bb_program;
+
+typedef struct bb_diagnostic {
+    const char *message;
+    uint32_t has_span;
+    uint32_t start_line;
+    uint32_t start_col;
+    uint32_t end_line;
+    uint32_t end_col;
+    uint32_t internal;
+} bb_diagnostic;
+
+uint32_t bb_abi_version(void);
+
+int32_t bb_compile(const uint8_t *src, size_t len, uint32_t dialect,
+                   bb_program **out);
+int32_t bb_run(const bb_program *program, uint32_t memory, int32_t *exit_code);
+const bb_diagnostic *bb_last_diagnostics(size_t *count);
+const uint8_t *bb_last_stdout(size_t *len);
+void bb_program_free(bb_program *program);
+
+#endif
@@ -0,0 +1,147 @@
+use crate::error::Error;
+use crate::heap::Memory;
+use crate::span::{Pos, Span, Spanned};
+use crate::types::TypeKind;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+// The standard library: functions every program can call without
+// declaring them. A program's own declaration of the same name shadows
+// the builtin. Each execution mode lowers a call to `Builtin::call`, so
+// they all agree on the results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Builtin {
+    Strlen,
+    Strcmp,
+    Abs,
+    Min,
+    Max,
+    Pow,
+    // The program's command-line arguments, the first being its name.
+    ArgCount,
+    GetArg,
+}
+
+// An argument to or result of a builtin.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i32),
+    // A `char[]`, which ends at its first NUL if it has one.
+    Chars(Vec<char>),
+}
+
+fn char_array() -> TypeKind {
+    let none = Span::new(Pos { line: 0, col: 0 }, Pos { line: 0, col: 0 });
+    TypeKind::Array(Box::new(Spanned::new(TypeKind::Char, none)))
+}
+
+// What a running program can see of the outside world.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Env {
+    pub args: Vec<String>,
+    // How the program's arrays are reclaimed.
+    pub memory: Memory,
+}
+
+impl Env {
+    pub fn new(program: &str, args: &[String]) -> Self {
+        let mut all = vec![program.to_string()];
+        all.extend_from_slice(args);
+        Env { args: all, memory: Memory::default() }
+    }
+
+    pub fn with_memory(mut self, memory: Memory) -> Self {
+        self.memory = memory;
+        self
+    }
+}
+
+// The characters of a string up to its terminator.
+fn string(chars: &[char]) -> &[char] {
+    let end = chars.iter().position(|&c| c == '\0').unwrap_or(chars.len());
+    &chars[..end]
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 8] = [Builtin::Strlen, Builtin::Strcmp, Builtin::Abs,
+                                   Builtin::Min, Builtin::Max, Builtin::Pow,
+                                   Builtin::ArgCount, Builtin::GetArg];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Builtin::Strlen => "strlen",
+            Builtin::Strcmp => "strcmp",
+            Builtin::Abs => "abs",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Pow => "pow",
+            Builtin::ArgCount => "arg_count",
+            Builtin::GetArg => "get_arg",
+        }
+    }
+
+    pub fn ret(self) -> TypeKind {
+        match self {
+            Builtin::GetArg => char_array(),
+            _ => TypeKind::Int,
+        }
+    }
+
+    pub fn params(self) -> Vec<TypeKind> {
+        match self {
+            Builtin::Strlen => vec![char_array()],
+            Builtin::Strcmp => vec![char_array(), char_array()],
+            Builtin::Abs | Builtin::GetArg => vec![TypeKind::Int],
+            Builtin::ArgCount => Vec::new(),
+            Builtin::Min | Builtin::Max | Builtin::Pow => vec![TypeKind::Int, TypeKind::Int],
+        }
+    }
+
+    pub fn signature(self) -> String {
+        let params: Vec<_> = self.params().iter().map(|p| p.to_string()).collect();
+        format!("{} {}({})", self.ret(), self.name(), params.join(", "))
+    }
+
+    // Arithmetic wraps on overflow, as it does everywhere else. A negative
+    // exponent truncates towards zero like repeated division would, and
+    // `strcmp` gives -1, 0 or 1. Arguments past the last are empty.
+    pub fn call(self, args: &[Value], env: &Env) -> Result<Value, Error> {
+        use Value::*;
+        let result = match (self, args) {
+            (Builtin::ArgCount, []) => env.args.len() as i32,
+            (Builtin::GetArg, [Int(i)]) => {
+                let arg = usize::try_from(*i).ok().and_then(|i| env.args.get(i));
+                return Ok(Chars(arg.map_or_else(Vec::new, |a| a.chars().collect())));
+            }
+            (Builtin::Strlen, [Chars(s)]) => string(s).len() as i32,
+            (Builtin::Strcmp, [Chars(a), Chars(b)]) => {
+                let (a, b) = (string(a), string(b));
+                match a.iter().zip(b).find(|(x, y)| x != y) {
+                    Some((x, y)) => *x as i32 - *y as i32,
+                    None => a.len() as i32 - b.len() as i32,
+                }
+                .signum()
+            }
+            (Builtin::Abs, [Int(n)]) => n.wrapping_abs(),
+            (Builtin::Min, [Int(a), Int(b)]) => *a.min(b),
+            (Builtin::Max, [Int(a), Int(b)]) => *a.max(b),
+            (Builtin::Pow, [Int(base), Int(exp)]) => match (*base, *exp) {
+                (base, exp) if exp >= 0 => base.wrapping_pow(exp as u32),
+                (1, _) => 1,
+                (-1, exp) => if exp % 2 == 0 { 1 } else { -1 },
+                // 1 / 0^n.
+                (0, _) => return Err(Error::DivisionByZero),
+                _ => 0,
+            },
+            _ => return Err(Error::Internal(format!(
+                "{} called with mismatched arguments", self.name()))),
+        };
+        Ok(Int(result))
+    }
+}
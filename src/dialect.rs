@@ -0,0 +1,37 @@
+use alloc::format;
+use alloc::string::String;
+use core::str::FromStr;
+use serde::Deserialize;
+
+// Which syntax a program may use. Strict accepts only the C subset, so
+// names such as `new` and `import` stay free for programs to use. Extended
+// adds bearbones' own syntax: `new` and `delete`, `auto`, `import` and
+// `@attributes`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageDialect {
+    #[default]
+    Strict,
+    Extended,
+}
+
+impl LanguageDialect {
+    pub const fn name(self) -> &'static str {
+        match self {
+            LanguageDialect::Strict => "strict",
+            LanguageDialect::Extended => "extended",
+        }
+    }
+}
+
+impl FromStr for LanguageDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(LanguageDialect::Strict),
+            "extended" => Ok(LanguageDialect::Extended),
+            _ => Err(format!("unknown dialect '{s}', expected strict or extended")),
+        }
+    }
+}
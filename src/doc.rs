@@ -0,0 +1,141 @@
+use crate::dialect::LanguageDialect;
+use crate::error::Error;
+use crate::preprocess;
+use crate::symbols::{SymbolKind, SymbolTable};
+use crate::timing;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            _ => Err(format!("unknown format '{s}', expected markdown or html")),
+        }
+    }
+}
+
+// A function or global and its doc comment, if it has one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocItem {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub signature: String,
+    // The `///` lines directly above the declaration, without the slashes.
+    pub docs: String,
+    pub line: usize,
+}
+
+// Text of each line that holds nothing but a `///` comment, by line number.
+fn doc_lines(src: &str) -> Result<HashMap<usize, String>, Error> {
+    let mut docs = HashMap::new();
+    for range in preprocess::comments(src)? {
+        let comment = &src[range.clone()];
+        let Some(text) = comment.strip_prefix("///") else { continue };
+        let line_start = src[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        if !src[line_start..range.start].trim().is_empty() {
+            continue;
+        }
+        let line = src[..range.start].matches('\n').count() + 1;
+        docs.insert(line, text.strip_prefix(' ').unwrap_or(text).trim_end().to_string());
+    }
+    Ok(docs)
+}
+
+// The functions and globals declared in `src`, in order, each with the doc
+// comment above it. A function declared more than once is listed at its
+// first declaration, with the first doc comment it was given.
+pub fn collect(src: &str, dialect: LanguageDialect) -> Result<Vec<DocItem>, Error> {
+    let docs = doc_lines(src)?;
+    let table = SymbolTable::from_source(src, dialect)?;
+
+    let mut items: Vec<DocItem> = Vec::new();
+    for symbol in table.symbols() {
+        if !matches!(symbol.kind, SymbolKind::Function | SymbolKind::Global) {
+            continue;
+        }
+        let line = symbol.span.start.line;
+        let mut lines = Vec::new();
+        while let Some(text) = docs.get(&(line - lines.len() - 1)) {
+            lines.push(text.as_str());
+        }
+        lines.reverse();
+        let text = lines.join("\n");
+        match items.iter_mut().find(|item| item.name == symbol.name) {
+            Some(item) if item.docs.is_empty() => item.docs = text,
+            Some(_) => {}
+            None => items.push(DocItem {
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                signature: symbol.signature(),
+                docs: text,
+                line,
+            }),
+        }
+    }
+    Ok(items)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn render_markdown(title: &str, items: &[DocItem]) -> String {
+    let mut out = format!("# {title}\n");
+    for (heading, kind) in [("Functions", SymbolKind::Function),
+                            ("Globals", SymbolKind::Global)] {
+        let items: Vec<_> = items.iter().filter(|item| item.kind == kind).collect();
+        if items.is_empty() {
+            continue;
+        }
+        let _ = write!(out, "\n## {heading}\n");
+        for item in items {
+            let _ = write!(out, "\n### `{}`\n", item.signature);
+            if !item.docs.is_empty() {
+                let _ = write!(out, "\n{}\n", item.docs);
+            }
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, items: &[DocItem]) -> String {
+    let mut out = format!("<h1>{}</h1>\n", escape_html(title));
+    for (heading, kind) in [("Functions", SymbolKind::Function),
+                            ("Globals", SymbolKind::Global)] {
+        let items: Vec<_> = items.iter().filter(|item| item.kind == kind).collect();
+        if items.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "<h2>{heading}</h2>");
+        for item in items {
+            let _ = writeln!(out, "<h3 id=\"{}\"><code>{}</code></h3>",
+                             item.name, escape_html(&item.signature));
+            // Blank lines separate paragraphs, as in Markdown.
+            for para in item.docs.split("\n\n").filter(|p| !p.trim().is_empty()) {
+                let _ = writeln!(out, "<p>{}</p>", escape_html(para.trim()));
+            }
+        }
+    }
+    out
+}
+
+// API documentation for the declarations in `src`, headed by `title`.
+pub fn document(title: &str, src: &str, format: Format, dialect: LanguageDialect)
+        -> Result<String, Error> {
+    let items = collect(src, dialect)?;
+    Ok(timing::time("doc", || match format {
+        Format::Markdown => render_markdown(title, &items),
+        Format::Html => render_html(title, &items),
+    }))
+}
@@ -0,0 +1,528 @@
+use crate::builtins::Env;
+use crate::dialect::LanguageDialect;
+use crate::error::Error;
+use crate::heap::Heap;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::log;
+use crate::preprocess::{self, Preprocessor, SourceMap};
+use crate::profile::Profiler;
+use crate::span::{Pos, Span};
+use crate::timing;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    Ir,
+    Bytecode,
+    Asm,
+}
+
+impl Emit {
+    pub const ALL: [Emit; 5] =
+        [Emit::Tokens, Emit::Ast, Emit::Ir, Emit::Bytecode, Emit::Asm];
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "ir" => Ok(Emit::Ir),
+            "bytecode" => Ok(Emit::Bytecode),
+            "asm" => Ok(Emit::Asm),
+            _ => Err(format!("unknown emit kind '{s}', expected one of \
+                            tokens, ast, ir, bytecode, asm")),
+        }
+    }
+}
+
+impl Emit {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::Ir => "ir",
+            Emit::Bytecode => "bbc",
+            Emit::Asm => "s",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Artifact {
+    Executable,
+    Object,
+    Asm,
+    Bytecode,
+}
+
+impl Artifact {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Artifact::Executable => std::env::consts::EXE_EXTENSION,
+            Artifact::Object => "o",
+            Artifact::Asm => "s",
+            Artifact::Bytecode => "bbc",
+        }
+    }
+}
+
+// Default output location: next to the input with the extension swapped,
+// e.g. `src/prog.bb` -> `src/prog.s`. Programs read from stdin are named
+// after `a.out`.
+pub fn output_path(input: &Path, extension: &str) -> PathBuf {
+    if input == Path::new(STDIN) {
+        return match extension {
+            "" => PathBuf::from("a.out"),
+            ext => Path::new("a").with_extension(ext),
+        };
+    }
+    input.with_extension(extension)
+}
+
+pub const SOURCE_EXTENSION: &str = "bb";
+
+// Input path that stands for standard input, as in `bearbones run -`.
+pub const STDIN: &str = "-";
+const STDIN_NAME: &str = "<stdin>";
+
+pub fn read_source(path: &Path) -> Result<String, Error> {
+    log!(Debug, "reading {}", path.display());
+    timing::time("read", || {
+        if path == Path::new(STDIN) {
+            return read_reader(io::stdin().lock());
+        }
+        fs::read_to_string(path).map_err(|e| Error::Io(path.to_path_buf(), e))
+    })
+}
+
+pub fn read_reader(mut reader: impl Read) -> Result<String, Error> {
+    let mut src = String::new();
+    reader.read_to_string(&mut src)
+            .map_err(|e| Error::Io(STDIN_NAME.into(), e))?;
+    Ok(src)
+}
+
+// One translation unit: a source file and where it came from. After
+// preprocessing, `map` traces lines of `src` back to their files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unit {
+    pub path: PathBuf,
+    pub src: String,
+    pub map: SourceMap,
+    pub dialect: LanguageDialect,
+}
+
+impl Unit {
+    pub fn new(path: impl Into<PathBuf>, src: impl Into<String>) -> Self {
+        Unit {
+            path: path.into(),
+            src: src.into(),
+            map: SourceMap::default(),
+            dialect: LanguageDialect::default(),
+        }
+    }
+
+    // Tags an error from this unit with the file it originated in.
+    pub fn tag(&self, e: Error) -> Error {
+        self.map.map_error(e, &self.path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let src = read_source(path)?;
+        if path == Path::new(STDIN) {
+            return Ok(Unit::new(STDIN_NAME, src));
+        }
+        Ok(Unit::new(path, src))
+    }
+}
+
+// Threads used for per-unit work; 0 means one per available core.
+static JOBS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_jobs(jobs: usize) {
+    JOBS.store(jobs, Ordering::Relaxed);
+}
+
+// How `path` is named inside artifacts. Reproducible builds make absolute
+// paths relative to the working directory, or drop all but the file name
+// when they lie outside it, so the checkout location does not leak in and
+// artifacts only depend on the inputs and the paths they were given by.
+pub fn artifact_path(path: &Path, reproducible: bool) -> PathBuf {
+    if !reproducible || path.is_relative() {
+        return path.to_path_buf();
+    }
+    let cwd = std::env::current_dir().unwrap_or_default();
+    match path.strip_prefix(&cwd) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.file_name().map_or_else(PathBuf::new, PathBuf::from),
+    }
+}
+
+fn jobs() -> usize {
+    match JOBS.load(Ordering::Relaxed) {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    }
+}
+
+// Applies `f` to every item on a pool of scoped threads. Results come back
+// in item order, so diagnostics are reported the same way on every run.
+// Passes run one at a time while being timed, so their memory use is
+// measured in isolation.
+fn par_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = jobs().min(items.len());
+    if threads <= 1 || timing::enabled() {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    // Workers report to the caller's subscriber, inside the caller's span.
+    #[cfg(feature = "tracing")]
+    let (dispatch, parent) =
+        (tracing::dispatcher::get_default(Clone::clone), tracing::Span::current());
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| {
+                #[cfg(feature = "tracing")]
+                let _dispatch = tracing::dispatcher::set_default(&dispatch);
+                #[cfg(feature = "tracing")]
+                let _span = parent.enter();
+                let mut done = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        return done;
+                    };
+                    done.push((i, f(item)));
+                }
+            }))
+            .collect();
+        for worker in workers {
+            let done = worker.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            for (i, result) in done {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results.into_iter().flatten().collect()
+}
+
+pub fn load_units(paths: &[PathBuf]) -> Result<Vec<Unit>, Error> {
+    paths.iter().map(|path| Unit::load(path)).collect()
+}
+
+// Source files at `path`: the file itself, or every `.bb` file under the
+// directory, sorted so builds are repeatable.
+pub fn find_sources(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let entries = fs::read_dir(dir).map_err(|e| Error::Io(dir.to_path_buf(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| Error::Io(dir.to_path_buf(), e))?.path();
+            if path.is_dir() {
+                walk(&path, found)?;
+            } else if path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION) {
+                found.push(path);
+            }
+        }
+        Ok(())
+    }
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut found = Vec::new();
+    walk(path, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+// Resolves `#include`s, conditionals and macros in each unit. Includes are
+// searched for next to the including file, then on the include path. The
+// units are then lexed in the options' dialect.
+pub fn preprocess_units(units: Vec<Unit>, options: &preprocess::Options)
+        -> Result<Vec<Unit>, Error> {
+    par_map(&units, |unit| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("unit", path = %unit.path.display()).entered();
+        log!(Verbose, "preprocessing {}", unit.path.display());
+        let out = timing::time("preprocess", || preprocess::preprocess_with(
+                    &unit.path, &unit.src, options))?;
+        Ok(Unit {
+            path: unit.path.clone(),
+            src: out.src,
+            map: out.map,
+            dialect: options.dialect,
+        })
+    })
+    .into_iter()
+    .collect()
+}
+
+pub fn compile_reader(reader: impl Read, output: &Path) -> Result<(), Error> {
+    build(&[Unit::new(STDIN_NAME, read_reader(reader)?)], output)
+}
+
+pub fn lex(src: &str, dialect: LanguageDialect) -> Result<Vec<Token>, Error> {
+    let tokens = timing::time("lex", || Lexer::new(src).with_dialect(dialect).scanner())?;
+    log!(Debug, "lexed {} tokens", tokens.len());
+    Ok(tokens)
+}
+
+// Front end only: runs every analysis stage that exists without producing
+// any output. Parsing and semantic analysis slot in here as they land.
+pub fn check(src: &str, dialect: LanguageDialect) -> Result<Vec<Token>, Error> {
+    let tokens = lex(src, dialect)?;
+    if let Some(token) = tokens.iter().find(|t| t.kind == TokenKind::Unknown) {
+        return Err(Error::UnexpectedChar(token.span.clone()));
+    }
+    Ok(tokens)
+}
+
+// Checks every unit in parallel, collecting the first error of each so one
+// bad file does not hide problems in the rest. Errors are tagged with their
+// path and listed in unit order.
+pub fn check_all(units: &[Unit]) -> Vec<Error> {
+    par_map(units, |unit| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("unit", path = %unit.path.display()).entered();
+        log!(Verbose, "checking {}", unit.path.display());
+        let error = check(&unit.src, unit.dialect).err();
+        #[cfg(feature = "tracing")]
+        if let Some(e) = &error {
+            tracing::warn!(error = %e, "check failed");
+        }
+        error.map(|e| unit.tag(e))
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+pub fn check_units(units: &[Unit]) -> Result<(), Error> {
+    match check_all(units).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg_attr(feature = "tracing",
+           tracing::instrument(skip_all, fields(output = %output.display()), err))]
+pub fn build(units: &[Unit], output: &Path) -> Result<(), Error> {
+    check_units(units)?;
+    log!(Verbose, "generating code for {}", output.display());
+    Err(Error::Unsupported("Code generation"))
+}
+
+// What the program sees as its arguments: the path of its first unit, as
+// named by `artifact_path`, then `args`.
+pub fn program_env(units: &[Unit], args: &[String], reproducible: bool) -> Env {
+    let program = units.first().map_or_else(String::new,
+                    |unit| artifact_path(&unit.path, reproducible).display().to_string());
+    Env::new(&program, args)
+}
+
+// The status a process exits with when `main` returns `status`, truncated
+// to a byte the same way the OS truncates it for a natively built program.
+pub fn exit_status(status: i32) -> u8 {
+    status as u8
+}
+
+// Runs the program in `env`, e.g. from `program_env`, and returns its exit
+// status.
+pub fn run(units: &[Unit], env: &Env) -> Result<i32, Error> {
+    run_with_stdout(units, env, &mut io::stdout())
+}
+
+// Like `run`, with the program's standard output going to `stdout`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+pub fn run_with_stdout(units: &[Unit], env: &Env, _stdout: &mut dyn io::Write)
+        -> Result<i32, Error> {
+    check_units(units)?;
+    let heap = Heap::default().with_memory(env.memory);
+    log!(Verbose, "running with arguments {:?} and {:?} memory", env.args, heap.memory());
+    Err(Error::Unsupported("Program execution"))
+}
+
+// Like `run`, recording the time spent in each function into `profiler`.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+pub fn run_profiled(units: &[Unit], env: &Env, _profiler: &mut Profiler)
+        -> Result<i32, Error> {
+    check_units(units)?;
+    let heap = Heap::default().with_memory(env.memory);
+    log!(Verbose, "profiling with arguments {:?} and {:?} memory", env.args, heap.memory());
+    Err(Error::Unsupported("Program execution"))
+}
+
+// Inputs larger than this are rejected by `compile_source` unread.
+pub const MAX_SOURCE_LEN: usize = 1 << 20;
+
+// A problem found by `compile_source`, detached from any file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    // Set when the compiler itself failed rather than the program.
+    pub internal: bool,
+}
+
+impl From<Error> for Diagnostic {
+    fn from(e: Error) -> Self {
+        let e = match e {
+            Error::InFile(_, e) => *e,
+            e => e,
+        };
+        Diagnostic {
+            message: e.to_string().trim_end().to_string(),
+            span: e.span().cloned(),
+            internal: matches!(e, Error::Internal(_)),
+        }
+    }
+}
+
+// Everything the pipeline produces for a program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Artifacts {
+    // The program after preprocessing, ready to run.
+    pub unit: Unit,
+    pub tokens: Vec<Token>,
+}
+
+fn utf8_error(src: &[u8], e: std::str::Utf8Error) -> Diagnostic {
+    let valid = &src[..e.valid_up_to()];
+    let line = valid.iter().filter(|&&b| b == b'\n').count() + 1;
+    let col = valid.len() - valid.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let span = Span::new(Pos { line, col }, Pos { line, col: col + 1 });
+    Diagnostic {
+        message: format!("Syntax Error: Invalid UTF-8 at {span}"),
+        span: Some(span),
+        internal: false,
+    }
+}
+
+// Runs the whole pipeline over untrusted input without touching the file
+// system. This never panics: oversized and non-UTF-8 input is reported,
+// preprocessing is bounded, and a panic inside a pass is caught and
+// reported as an internal error.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(len = src.len())))]
+pub fn compile_source(src: &[u8], dialect: LanguageDialect)
+        -> Result<Artifacts, Vec<Diagnostic>> {
+    if src.len() > MAX_SOURCE_LEN {
+        return Err(vec![Diagnostic {
+            message: format!("Error: Input is larger than {MAX_SOURCE_LEN} bytes"),
+            span: None,
+            internal: false,
+        }]);
+    }
+    let src = std::str::from_utf8(src).map_err(|e| vec![utf8_error(src, e)])?;
+    let path = Path::new(STDIN_NAME);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let out = Preprocessor::new(&[]).without_includes().run(path, src)?;
+        let tokens = check(&out.src, dialect).map_err(|e| out.map.map_error(e, path))?;
+        let unit = Unit { path: path.to_path_buf(), src: out.src, map: out.map, dialect };
+        Ok(Artifacts { unit, tokens })
+    }));
+    let result = result.unwrap_or_else(|payload| {
+        let msg = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "compiler panicked".to_string());
+        Err(Error::Internal(msg))
+    });
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &result {
+        tracing::warn!(error = %e, "compile failed");
+    }
+    result.map_err(|e| vec![e.into()])
+}
+
+pub fn render_tokens(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        let (start, end) = (&token.span.start, &token.span.end);
+        let _ = writeln!(out, "{}:{}-{}:{}\t{:?}",
+            start.line, start.col, end.line, end.col, token.kind);
+    }
+    out
+}
+
+// Runs the pipeline up to `stage` and returns a textual dump of its output.
+pub fn emit(src: &str, stage: Emit, dialect: LanguageDialect) -> Result<String, Error> {
+    match stage {
+        Emit::Tokens => {
+            let tokens = lex(src, dialect)?;
+            Ok(timing::time("emit", || render_tokens(&tokens)))
+        }
+        Emit::Ast => Err(Error::Unsupported("Emitting the AST")),
+        Emit::Ir => Err(Error::Unsupported("Emitting IR")),
+        Emit::Bytecode => Err(Error::Unsupported("Emitting bytecode")),
+        Emit::Asm => Err(Error::Unsupported("Emitting assembly")),
+    }
+}
+
+// Like `emit`, over several units. Each dump is headed by its unit's path
+// when there is more than one, named as by `artifact_path`.
+pub fn emit_units(units: &[Unit], stage: Emit, reproducible: bool) -> Result<String, Error> {
+    let mut out = String::new();
+    for unit in units {
+        log!(Verbose, "emitting {:?} for {}", stage, unit.path.display());
+        if units.len() > 1 {
+            let _ = writeln!(out, "==> {} <==", artifact_path(&unit.path, reproducible).display());
+        }
+        out += &emit(&unit.src, stage, unit.dialect).map_err(|e| unit.tag(e))?;
+    }
+    Ok(out)
+}
+
+// Writes every stage the pipeline can produce for each unit into `dir`,
+// one file per stage at the unit's path as named by `artifact_path`, e.g.
+// `src/prog.tokens`. Stages that are not implemented yet are skipped.
+// Returns the files written.
+pub fn dump_stages(units: &[Unit], dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    // Everything is emitted and checked before the first write, so a
+    // failure leaves no partial dump behind.
+    let mut dumps = Vec::new();
+    let mut targets = HashSet::new();
+    for unit in units {
+        let name: PathBuf = match unit.path.as_path() {
+            path if path == Path::new(STDIN_NAME) => PathBuf::from(STDIN),
+            // `..` and roots would lead out of `dir`.
+            path => artifact_path(path, true).components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect(),
+        };
+        for stage in Emit::ALL {
+            let out = match emit(&unit.src, stage, unit.dialect) {
+                Ok(out) => out,
+                Err(Error::Unsupported(_)) => continue,
+                Err(e) => return Err(unit.tag(e)),
+            };
+            let path = dir.join(output_path(&name, stage.extension()));
+            if !targets.insert(path.clone()) {
+                return Err(Error::Io(path, io::Error::new(io::ErrorKind::AlreadyExists,
+                    format!("{} dumps to the same file as another input",
+                            unit.path.display()))));
+            }
+            dumps.push((path, out));
+        }
+    }
+    let mut written = Vec::new();
+    for (path, out) in dumps {
+        log!(Verbose, "dumping {}", path.display());
+        let parent = path.parent().unwrap_or(dir);
+        fs::create_dir_all(parent).map_err(|e| Error::Io(parent.to_path_buf(), e))?;
+        fs::write(&path, out).map_err(|e| Error::Io(path.clone(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
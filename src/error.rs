@@ -1,5 +1,11 @@
-use crate::lexer::{Span};
-use std::fmt;
+use crate::span::Span;
+use alloc::string::String;
+use core::fmt;
+use serde::Serialize;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum Error {
@@ -10,13 +16,125 @@ pub enum Error {
     CharExpected(Span),
     UnexpectedEndOfInput,
     MainNotFound,
+    UnknownType(Span),
+    RecursiveTypedef(Span),
+    UnexpectedChar(Span),
+    InvalidNumber(Span),
+    #[cfg(feature = "std")]
+    Io(PathBuf, io::Error),
+    Unsupported(&'static str),
+    #[cfg(feature = "std")]
+    InFile(PathBuf, Box<Error>),
+    #[cfg(feature = "std")]
+    InvalidConfig(PathBuf, String),
+    IncludeNotFound(Span, String),
+    IncludeTooDeep(Span),
+    InvalidDirective(Span),
+    UnknownDirective(Span),
+    UnexpectedDirective(Span),
+    UnterminatedConditional(Span),
+    UnterminatedMacroCall(Span, String),
+    MacroArgCount(Span, String, usize, usize),
+    UnterminatedComment(Span),
+    ExpansionTooLarge(Span),
+    Internal(String),
+    IndexOutOfBounds(i32, usize),
+    InvalidArrayLength(i32),
+    HeapExhausted(usize),
+    DanglingReference,
+    DivisionByZero,
 }
 
-impl fmt::Display for Span {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "line {}, columns {} - {}",
-            self.start.line, self.start.col, self.end.col
-        )
+// Broad classes of error, each with its own process exit status.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    // Lexing, preprocessing and parsing.
+    Syntax,
+    Type,
+    // The compiled program failed while running.
+    Program,
+    // Reading inputs or configuration.
+    Io,
+    Unsupported,
+    // A bug in the compiler.
+    Internal,
+}
+
+impl ErrorKind {
+    // 0 is success, 1 a failed check such as `fmt --check` and 2 a usage
+    // error, so error kinds start at 3.
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Syntax => 3,
+            ErrorKind::Type => 4,
+            ErrorKind::Program => 5,
+            ErrorKind::Io => 6,
+            ErrorKind::Unsupported => 7,
+            ErrorKind::Internal => 8,
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        use Error::*;
+        match self {
+            UnknownType(_) | RecursiveTypedef(_) | MainNotFound => ErrorKind::Type,
+            #[cfg(feature = "std")]
+            Io(..) | InvalidConfig(..) => ErrorKind::Io,
+            Unsupported(_) => ErrorKind::Unsupported,
+            Internal(_) => ErrorKind::Internal,
+            IndexOutOfBounds(..) | InvalidArrayLength(_) | HeapExhausted(_)
+            | DanglingReference | DivisionByZero => ErrorKind::Program,
+            #[cfg(feature = "std")]
+            InFile(_, e) => e.kind(),
+            _ => ErrorKind::Syntax,
+        }
+    }
+
+    pub fn span(&self) -> Option<&Span> {
+        use Error::*;
+        match self {
+            EmptyChar(span) | CharNotAscii(span) | EscNotFound(span)
+            | CharNotTerminated(span) | CharExpected(span) | UnknownType(span)
+            | RecursiveTypedef(span) | UnexpectedChar(span) | InvalidNumber(span)
+            | IncludeNotFound(span, _) | IncludeTooDeep(span)
+            | InvalidDirective(span) | UnknownDirective(span)
+            | UnexpectedDirective(span) | UnterminatedConditional(span)
+            | UnterminatedMacroCall(span, _) | MacroArgCount(span, ..)
+            | UnterminatedComment(span) | ExpansionTooLarge(span)
+            => Some(span),
+            #[cfg(feature = "std")]
+            InFile(_, e) => e.span(),
+            _ => None,
+        }
+    }
+
+    pub fn span_mut(&mut self) -> Option<&mut Span> {
+        use Error::*;
+        match self {
+            EmptyChar(span) | CharNotAscii(span) | EscNotFound(span)
+            | CharNotTerminated(span) | CharExpected(span) | UnknownType(span)
+            | RecursiveTypedef(span) | UnexpectedChar(span) | InvalidNumber(span)
+            | IncludeNotFound(span, _) | IncludeTooDeep(span)
+            | InvalidDirective(span) | UnknownDirective(span)
+            | UnexpectedDirective(span) | UnterminatedConditional(span)
+            | UnterminatedMacroCall(span, _) | MacroArgCount(span, ..)
+            | UnterminatedComment(span) | ExpansionTooLarge(span)
+            => Some(span),
+            #[cfg(feature = "std")]
+            InFile(_, e) => e.span_mut(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn in_file(self, path: &Path) -> Self {
+        match self {
+            Error::InFile(..) | Error::Io(..) => self,
+            _ => Error::InFile(path.to_path_buf(), Box::new(self)),
+        }
     }
 }
 
@@ -31,6 +149,33 @@ impl fmt::Display for Error {
             CharExpected(span) => { writeln!(f, "Syntax Error: Character literal not found at {span}") }
             UnexpectedEndOfInput => { writeln!(f, "Token Error: ") }
             MainNotFound => { writeln!(f, "Error: 'main' function cannot be found") }
+            UnknownType(span) => { writeln!(f, "Type Error: Unknown type name at {span}") }
+            RecursiveTypedef(span) => { writeln!(f, "Type Error: Typedef refers to itself at {span}") }
+            UnexpectedChar(span) => { writeln!(f, "Syntax Error: Unexpected character at {span}") }
+            InvalidNumber(span) => { writeln!(f, "Syntax Error: Number is not an int or is out of range at {span}") }
+            #[cfg(feature = "std")]
+            Io(path, e) => { writeln!(f, "Error: {}: {e}", path.display()) }
+            Unsupported(what) => { writeln!(f, "Error: {what} is not supported yet") }
+            #[cfg(feature = "std")]
+            InFile(path, e) => { write!(f, "{}: {e}", path.display()) }
+            #[cfg(feature = "std")]
+            InvalidConfig(path, msg) => { writeln!(f, "Config Error: {}: {msg}", path.display()) }
+            IncludeNotFound(span, name) => { writeln!(f, "Preprocessor Error: Cannot find include file '{name}' at {span}") }
+            IncludeTooDeep(span) => { writeln!(f, "Preprocessor Error: Includes nested too deeply at {span}") }
+            InvalidDirective(span) => { writeln!(f, "Preprocessor Error: Malformed directive at {span}") }
+            UnknownDirective(span) => { writeln!(f, "Preprocessor Error: Unknown directive at {span}") }
+            UnexpectedDirective(span) => { writeln!(f, "Preprocessor Error: Directive without matching #ifdef at {span}") }
+            UnterminatedConditional(span) => { writeln!(f, "Preprocessor Error: Conditional not terminated by #endif at {span}") }
+            UnterminatedMacroCall(span, name) => { writeln!(f, "Preprocessor Error: Arguments to macro '{name}' not closed on the same line at {span}") }
+            MacroArgCount(span, name, expected, found) => { writeln!(f, "Preprocessor Error: Macro '{name}' takes {expected} arguments but {found} were given at {span}") }
+            UnterminatedComment(span) => { writeln!(f, "Preprocessor Error: Comment not terminated by */ at {span}") }
+            ExpansionTooLarge(span) => { writeln!(f, "Preprocessor Error: Macro expansion too large or too deeply nested at {span}") }
+            Internal(msg) => { writeln!(f, "Internal Error: {msg}") }
+            IndexOutOfBounds(index, len) => { writeln!(f, "Runtime Error: Index {index} is out of bounds for an array of length {len}") }
+            InvalidArrayLength(len) => { writeln!(f, "Runtime Error: Cannot create an array of length {len}") }
+            HeapExhausted(limit) => { writeln!(f, "Runtime Error: Heap limit of {limit} elements exceeded") }
+            DanglingReference => { writeln!(f, "Runtime Error: Array used after it was freed") }
+            DivisionByZero => { writeln!(f, "Runtime Error: Division by zero") }
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,239 @@
+use crate::dialect::LanguageDialect;
+use crate::error::Error;
+use crate::lexer::TokenKind;
+use crate::span::Span;
+use crate::symbols::{SymbolKind, SymbolTable};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span as TextSpan};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+use std::io;
+
+// One row of the tree. Nodes are stored in preorder, so a node's
+// descendants directly follow it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub label: String,
+    pub span: Option<Span>,
+    pub depth: usize,
+    pub parent: Option<usize>,
+    pub expanded: bool,
+    // For identifier tokens, the node of the declaration they refer to.
+    target: Option<usize>,
+}
+
+// State of the explorer, independent of the terminal: a tree of what the
+// front end knows about the source, and which row is selected.
+pub struct Explorer {
+    pub src: String,
+    nodes: Vec<Node>,
+    selected: usize,
+}
+
+impl Explorer {
+    // The tree holds the declarations and the token stream until the
+    // parser produces an AST, and a placeholder for the IR.
+    pub fn new(src: &str, dialect: LanguageDialect) -> Result<Self, Error> {
+        let table = SymbolTable::from_source(src, dialect)?;
+        let mut explorer = Explorer { src: src.to_string(), nodes: Vec::new(), selected: 0 };
+
+        let decls = explorer.push("Declarations".to_string(), None, None);
+        let mut decl_nodes = vec![0; table.symbols().len()];
+        for (i, symbol) in table.symbols().iter().enumerate() {
+            if symbol.owner.is_some() {
+                continue;
+            }
+            decl_nodes[i] = explorer.push(symbol.signature(),
+                                          Some(symbol.span.clone()), Some(decls));
+            for (j, child) in table.symbols().iter().enumerate()
+                    .filter(|(_, c)| c.owner == Some(i)) {
+                let kind = match child.kind {
+                    SymbolKind::Parameter => "parameter",
+                    _ => "local",
+                };
+                decl_nodes[j] = explorer.push(format!("{} ({kind})", child.signature()),
+                                              Some(child.span.clone()),
+                                              Some(decl_nodes[i]));
+            }
+        }
+
+        let tokens = explorer.push("Tokens".to_string(), None, None);
+        for (i, token) in table.tokens().iter().enumerate() {
+            let (start, end) = (&token.span.start, &token.span.end);
+            let label = format!("{}:{}-{}:{} {:?}",
+                start.line, start.col, end.line, end.col, token.kind);
+            let node = explorer.push(label, Some(token.span.clone()), Some(tokens));
+            if matches!(token.kind, TokenKind::Id(_)) {
+                let symbols = table.symbols();
+                explorer.nodes[node].target = table.resolve(i)
+                    .and_then(|s| symbols.iter().position(|t| std::ptr::eq(s, t)))
+                    .map(|s| decl_nodes[s]);
+            }
+        }
+
+        let ir = explorer.push("IR".to_string(), None, None);
+        explorer.push("not generated yet".to_string(), None, Some(ir));
+        explorer.nodes[decls].expanded = true;
+        Ok(explorer)
+    }
+
+    fn push(&mut self, label: String, span: Option<Span>, parent: Option<usize>)
+            -> usize {
+        let depth = parent.map_or(0, |p| self.nodes[p].depth + 1);
+        self.nodes.push(Node { label, span, depth, parent, expanded: false, target: None });
+        self.nodes.len() - 1
+    }
+
+    fn has_children(&self, node: usize) -> bool {
+        self.nodes.get(node + 1).is_some_and(|n| n.parent == Some(node))
+    }
+
+    fn is_visible(&self, node: usize) -> bool {
+        let mut parent = self.nodes[node].parent;
+        while let Some(p) = parent {
+            if !self.nodes[p].expanded {
+                return false;
+            }
+            parent = self.nodes[p].parent;
+        }
+        true
+    }
+
+    // Indices of the rows currently shown, in order.
+    pub fn visible(&self) -> Vec<usize> {
+        (0..self.nodes.len()).filter(|&n| self.is_visible(n)).collect()
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn selected(&self) -> &Node {
+        &self.nodes[self.selected]
+    }
+
+    pub fn move_down(&mut self) {
+        if let Some(next) = (self.selected + 1..self.nodes.len()).find(|&n| self.is_visible(n)) {
+            self.selected = next;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if let Some(prev) = (0..self.selected).rev().find(|&n| self.is_visible(n)) {
+            self.selected = prev;
+        }
+    }
+
+    pub fn expand(&mut self) {
+        if self.has_children(self.selected) {
+            self.nodes[self.selected].expanded = true;
+        }
+    }
+
+    // Collapses the selected node, or moves to its parent if it is
+    // already collapsed.
+    pub fn collapse(&mut self) {
+        let node = &mut self.nodes[self.selected];
+        if node.expanded {
+            node.expanded = false;
+        } else if let Some(parent) = node.parent {
+            self.selected = parent;
+        }
+    }
+
+    // Selects the declaration an identifier token refers to, expanding
+    // the tree down to it.
+    pub fn jump(&mut self) {
+        let Some(target) = self.selected().target else { return };
+        let mut parent = self.nodes[target].parent;
+        while let Some(p) = parent {
+            self.nodes[p].expanded = true;
+            parent = self.nodes[p].parent;
+        }
+        self.selected = target;
+    }
+
+    fn tree(&self) -> (List<'_>, ListState) {
+        let visible = self.visible();
+        let items: Vec<ListItem> = visible.iter().map(|&n| {
+            let node = &self.nodes[n];
+            let marker = match (self.has_children(n), node.expanded) {
+                (false, _) => "  ",
+                (true, false) => "▸ ",
+                (true, true) => "▾ ",
+            };
+            ListItem::new(format!("{}{marker}{}", "  ".repeat(node.depth), node.label))
+        }).collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Tree"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let state = ListState::default()
+            .with_selected(visible.iter().position(|&n| n == self.selected));
+        (list, state)
+    }
+
+    // The source with the selected node's span reversed, scrolled so the
+    // span is in view.
+    fn source(&self, height: u16) -> Paragraph<'_> {
+        let span = self.selected().span.as_ref();
+        let lines: Vec<Line> = self.src.lines().enumerate().map(|(i, text)| {
+            let Some(span) = span.filter(|s| s.start.line == i + 1) else {
+                return Line::raw(text);
+            };
+            let start = span.start.col.min(text.len());
+            let end = span.end.col.clamp(start, text.len());
+            Line::from(vec![
+                TextSpan::raw(&text[..start]),
+                TextSpan::styled(&text[start..end],
+                    Style::default().add_modifier(Modifier::REVERSED)),
+                TextSpan::raw(&text[end..]),
+            ])
+        }).collect();
+        let line = span.map_or(0, |s| s.start.line.saturating_sub(1));
+        let scroll = line.saturating_sub(usize::from(height / 2));
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Source"))
+            .scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0))
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, help] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+            .areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(45),
+                                                Constraint::Percentage(55)])
+            .areas(main);
+        let (tree, mut state) = self.tree();
+        frame.render_stateful_widget(tree, left, &mut state);
+        frame.render_widget(self.source(right.height), right);
+        frame.render_widget(Paragraph::new(
+            "↑/↓ move  →/enter expand  ← collapse  d go to declaration  q quit"), help);
+    }
+}
+
+// Runs the explorer on the terminal until the user quits.
+pub fn run(src: &str, dialect: LanguageDialect) -> Result<(), Error> {
+    let mut explorer = Explorer::new(src, dialect)?;
+    let mut terminal = ratatui::init();
+    let result = (|| -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| explorer.draw(frame))?;
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => explorer.move_down(),
+                KeyCode::Up | KeyCode::Char('k') => explorer.move_up(),
+                KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => explorer.expand(),
+                KeyCode::Left | KeyCode::Char('h') => explorer.collapse(),
+                KeyCode::Char('d') => explorer.jump(),
+                _ => {}
+            }
+        }
+    })();
+    ratatui::restore();
+    result.map_err(|e| Error::Io("terminal".into(), e))
+}
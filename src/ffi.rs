@@ -0,0 +1,210 @@
+use crate::dialect::LanguageDialect;
+use crate::driver::{self, Diagnostic, Unit};
+use crate::heap::Memory;
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+// Bumped whenever a struct or signature below changes. The layout of
+// everything here is mirrored in `include/bearbones.h`.
+pub const BB_ABI_VERSION: u32 = 3;
+
+pub const BB_OK: i32 = 0;
+pub const BB_COMPILE_ERROR: i32 = 1;
+pub const BB_RUN_ERROR: i32 = 2;
+pub const BB_INVALID_ARGUMENT: i32 = 3;
+
+pub const BB_DIALECT_STRICT: u32 = 0;
+pub const BB_DIALECT_EXTENDED: u32 = 1;
+
+pub const BB_MEMORY_MANUAL: u32 = 0;
+pub const BB_MEMORY_GC: u32 = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BbDiagnostic {
+    // NUL-terminated, owned by the library.
+    pub message: *const c_char,
+    // Zero when the diagnostic has no location; positions are then zero.
+    pub has_span: u32,
+    pub start_line: u32,
+    pub start_col: u32,
+    pub end_line: u32,
+    pub end_col: u32,
+    // Nonzero when the compiler itself failed rather than the program.
+    pub internal: u32,
+}
+
+// A compiled program, opaque to C.
+pub struct BbProgram {
+    unit: Unit,
+}
+
+// What the last call on this thread left behind. Pointers handed out
+// stay valid until the next `bb_compile` or `bb_run` on the same thread.
+#[derive(Default)]
+struct Last {
+    messages: Vec<CString>,
+    diagnostics: Vec<BbDiagnostic>,
+    stdout: Vec<u8>,
+}
+
+thread_local! {
+    static LAST: RefCell<Last> = RefCell::new(Last::default());
+}
+
+fn set_diagnostics(diags: &[Diagnostic]) {
+    LAST.with_borrow_mut(|last| {
+        // Interior NULs cannot be represented, so they end the message.
+        last.messages = diags.iter()
+            .map(|d| CString::new(d.message.split('\0').next().unwrap_or(""))
+                        .unwrap_or_default())
+            .collect();
+        last.diagnostics = diags.iter().zip(&last.messages)
+            .map(|(d, message)| {
+                let pos = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+                let (has_span, start_line, start_col, end_line, end_col) =
+                    match &d.span {
+                        Some(s) => (1, pos(s.start.line), pos(s.start.col),
+                                    pos(s.end.line), pos(s.end.col)),
+                        None => (0, 0, 0, 0, 0),
+                    };
+                BbDiagnostic {
+                    message: message.as_ptr(), has_span, start_line, start_col,
+                    end_line, end_col, internal: u32::from(d.internal),
+                }
+            })
+            .collect();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn bb_abi_version() -> u32 {
+    BB_ABI_VERSION
+}
+
+/// Compiles `len` bytes of source at `src` in `dialect`, one of the
+/// `BB_DIALECT_` constants. On success `*out` receives a
+/// program to pass to `bb_run` and later `bb_program_free`; otherwise the
+/// problems are available from `bb_last_diagnostics`.
+///
+/// # Safety
+///
+/// `src` must point to `len` readable bytes and `out` must be a valid
+/// pointer to write a program pointer to.
+#[no_mangle]
+pub unsafe extern "C" fn bb_compile(src: *const u8, len: usize, dialect: u32,
+                                    out: *mut *mut BbProgram) -> i32 {
+    let dialect = match dialect {
+        BB_DIALECT_STRICT => LanguageDialect::Strict,
+        BB_DIALECT_EXTENDED => LanguageDialect::Extended,
+        _ => return BB_INVALID_ARGUMENT,
+    };
+    if src.is_null() || out.is_null() {
+        return BB_INVALID_ARGUMENT;
+    }
+    let src = std::slice::from_raw_parts(src, len);
+    match driver::compile_source(src, dialect) {
+        Ok(artifacts) => {
+            set_diagnostics(&[]);
+            *out = Box::into_raw(Box::new(BbProgram { unit: artifacts.unit }));
+            BB_OK
+        }
+        Err(diags) => {
+            set_diagnostics(&diags);
+            *out = ptr::null_mut();
+            BB_COMPILE_ERROR
+        }
+    }
+}
+
+/// Runs a compiled program with `memory`, one of the `BB_MEMORY_`
+/// constants, storing its exit status in `*exit_code`. Its
+/// standard output is captured and available from `bb_last_stdout`.
+///
+/// # Safety
+///
+/// `program` must come from `bb_compile` and not have been freed, and
+/// `exit_code` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn bb_run(program: *const BbProgram, memory: u32,
+                                exit_code: *mut i32) -> i32 {
+    let memory = match memory {
+        BB_MEMORY_MANUAL => Memory::Manual,
+        BB_MEMORY_GC => Memory::Collected,
+        _ => return BB_INVALID_ARGUMENT,
+    };
+    let (Some(program), false) = (program.as_ref(), exit_code.is_null()) else {
+        return BB_INVALID_ARGUMENT;
+    };
+    let mut stdout = Vec::new();
+    let units = std::slice::from_ref(&program.unit);
+    let env = driver::program_env(units, &[], false).with_memory(memory);
+    let result = panic::catch_unwind(AssertUnwindSafe(||
+        driver::run_with_stdout(units, &env, &mut stdout)));
+    let result = match result {
+        Ok(result) => result.map_err(Diagnostic::from),
+        Err(_) => Err(Diagnostic {
+            message: "Internal Error: compiler panicked".to_string(),
+            span: None,
+            internal: true,
+        }),
+    };
+    LAST.with_borrow_mut(|last| last.stdout = stdout);
+    match result {
+        Ok(status) => {
+            set_diagnostics(&[]);
+            *exit_code = status;
+            BB_OK
+        }
+        Err(diag) => {
+            set_diagnostics(&[diag]);
+            BB_RUN_ERROR
+        }
+    }
+}
+
+/// The diagnostics from the last `bb_compile` or `bb_run` on this thread,
+/// with their number stored in `*count`.
+///
+/// # Safety
+///
+/// `count` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn bb_last_diagnostics(count: *mut usize)
+        -> *const BbDiagnostic {
+    let (diags, len) = LAST.with_borrow(|last|
+        (last.diagnostics.as_ptr(), last.diagnostics.len()));
+    if !count.is_null() {
+        *count = len;
+    }
+    diags
+}
+
+/// The output captured by the last `bb_run` on this thread, with its length
+/// in bytes stored in `*len`. It is not NUL-terminated.
+///
+/// # Safety
+///
+/// `len` must be a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn bb_last_stdout(len: *mut usize) -> *const u8 {
+    let (out, n) = LAST.with_borrow(|last| (last.stdout.as_ptr(), last.stdout.len()));
+    if !len.is_null() {
+        *len = n;
+    }
+    out
+}
+
+/// Frees a program from `bb_compile`. Null is ignored.
+///
+/// # Safety
+///
+/// `program` must come from `bb_compile` and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn bb_program_free(program: *mut BbProgram) {
+    if !program.is_null() {
+        drop(Box::from_raw(program));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::dialect::LanguageDialect;
+use crate::driver;
+use crate::error::Error;
+use crate::lexer::{Keyword, Operator, Token, TokenKind};
+use crate::preprocess;
+use crate::timing;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BraceStyle {
+    SameLine,
+    NextLine,
+}
+
+// Options read from `.bbfmt.toml`. Missing keys keep their defaults.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FmtConfig {
+    pub indent_width: usize,
+    pub brace_style: BraceStyle,
+    pub max_width: usize,
+}
+
+impl Default for FmtConfig {
+    fn default() -> Self {
+        FmtConfig {
+            indent_width: 4,
+            brace_style: BraceStyle::SameLine,
+            max_width: 100,
+        }
+    }
+}
+
+impl FmtConfig {
+    pub const FILE_NAME: &'static str = ".bbfmt.toml";
+
+    pub fn from_toml(src: &str, path: &Path) -> Result<Self, Error> {
+        toml::from_str(src)
+            .map_err(|e| Error::InvalidConfig(path.to_path_buf(), e.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let src = fs::read_to_string(path)
+                    .map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        Self::from_toml(&src, path)
+    }
+
+    // Uses the nearest `.bbfmt.toml` in `dir` or its ancestors, falling
+    // back to the defaults when there is none.
+    pub fn discover(dir: &Path) -> Result<Self, Error> {
+        // Made absolute first, or `.` would have no ancestors to search.
+        let dir = std::path::absolute(dir).map_err(|e| Error::Io(dir.to_path_buf(), e))?;
+        for dir in dir.ancestors() {
+            let path = dir.join(Self::FILE_NAME);
+            if path.is_file() {
+                return Self::load(&path);
+            }
+        }
+        Ok(Self::default())
+    }
+}
+
+// A directive or comment on lines of its own, printed as written.
+struct Verbatim<'a> {
+    line: usize,
+    end_line: usize,
+    text: &'a str,
+    // Comments are indented with the code; directives never are.
+    indent: bool,
+}
+
+struct Formatter<'a> {
+    tokens: &'a [Token],
+    // In line order.
+    verbatim: &'a [Verbatim<'a>],
+    // Comments that follow code on their line, each with the index of the
+    // token before it, in source order.
+    trailing: &'a [(usize, &'a str)],
+    config: &'a FmtConfig,
+    out: String,
+    depth: usize,
+    parens: usize,
+    // One entry per open brace, true when the block is the body of a `do`.
+    blocks: Vec<bool>,
+    line_start: bool,
+    unary: bool,
+    // Set by a `{` that is directly followed by its `}`.
+    empty_block: bool,
+}
+
+fn is_op(kind: &TokenKind, op: Operator) -> bool {
+    matches!(kind, TokenKind::Operator(o) if *o == op)
+}
+
+fn is_keyword(kind: &TokenKind, kw: Keyword) -> bool {
+    matches!(kind, TokenKind::Keyword(k) if *k == kw)
+}
+
+// `-`, `+` and `!` are prefix operators unless they follow an operand.
+fn is_unary(prev: Option<&TokenKind>, cur: &TokenKind) -> bool {
+    if !(is_op(cur, Operator::Sub) || is_op(cur, Operator::Add)
+            || is_op(cur, Operator::Not)) {
+        return false;
+    }
+    match prev {
+        None => true,
+        Some(TokenKind::Operator(op)) =>
+            !matches!(op, Operator::RightParen | Operator::RightBrace
+                          | Operator::RightBracket),
+        Some(TokenKind::Keyword(_)) => true,
+        Some(_) => false,
+    }
+}
+
+impl<'a> Formatter<'a> {
+    fn new(tokens: &'a [Token], verbatim: &'a [Verbatim<'a>],
+            trailing: &'a [(usize, &'a str)], config: &'a FmtConfig) -> Self {
+        Formatter {
+            tokens,
+            verbatim,
+            trailing,
+            config,
+            out: String::new(),
+            depth: 0,
+            parens: 0,
+            blocks: Vec::new(),
+            line_start: true,
+            unary: false,
+            empty_block: false,
+        }
+    }
+
+    fn newline(&mut self) {
+        if !self.line_start {
+            self.out.push('\n');
+            self.line_start = true;
+        }
+    }
+
+    fn indent(&mut self, depth: usize) {
+        let width = depth * self.config.indent_width;
+        self.out.extend(std::iter::repeat_n(' ', width));
+    }
+
+    fn line_len(&self) -> usize {
+        self.out.len() - self.out.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn space_before(&self, prev: &TokenKind, cur: &TokenKind) -> bool {
+        if self.unary || is_op(prev, Operator::LeftParen)
+                || is_op(prev, Operator::LeftBracket) || is_op(prev, Operator::Dot)
+                || is_op(prev, Operator::At) {
+            return false;
+        }
+        match cur {
+            TokenKind::Operator(Operator::RightParen | Operator::Comma
+                | Operator::Semicolon | Operator::Dot
+                | Operator::LeftBracket | Operator::RightBracket) => false,
+            TokenKind::Operator(Operator::LeftParen) =>
+                !matches!(prev, TokenKind::Id(_)),
+            _ => true,
+        }
+    }
+
+    // Prints the directives and comments above `line` on lines of their
+    // own, keeping a blank line before each one that had it, except at the
+    // start of a block. Comments are indented to `depth`. Returns the last
+    // line printed.
+    fn verbatim_before(&mut self, line: usize, mut last: Option<usize>,
+                       mut after_brace: bool, depth: usize) -> Option<usize> {
+        while let Some(item) = self.verbatim.first() {
+            if item.line >= line {
+                break;
+            }
+            self.newline();
+            if last.is_some_and(|l| item.line > l + 1) && !after_brace {
+                self.out.push('\n');
+            }
+            if item.indent {
+                self.indent(depth);
+            }
+            self.out.push_str(item.text);
+            self.out.push('\n');
+            last = Some(item.end_line);
+            after_brace = false;
+            self.verbatim = &self.verbatim[1..];
+        }
+        last
+    }
+
+    // Prints the comments that follow token `i` on its line. A line
+    // comment ends the line.
+    fn trailing_after(&mut self, i: usize) {
+        while let Some(&(token, text)) = self.trailing.first() {
+            if token != i {
+                break;
+            }
+            self.out.push(' ');
+            self.out.push_str(text);
+            if text.starts_with("//") {
+                self.newline();
+            }
+            self.trailing = &self.trailing[1..];
+        }
+    }
+
+    // Whether the `{` at `i` is directly followed by its `}`, with no
+    // comment or directive between them.
+    fn is_empty_block(&self, i: usize) -> bool {
+        let Some(next) = self.tokens.get(i + 1) else { return false };
+        is_op(&next.kind, Operator::RightBrace)
+            && self.trailing.first().is_none_or(|&(token, _)| token != i)
+            && self.verbatim.first().is_none_or(|v| v.line >= next.span.start.line)
+    }
+
+    fn write(&mut self, i: usize) {
+        let cur = &self.tokens[i].kind;
+        let prev = i.checked_sub(1).map(|p| &self.tokens[p]);
+        let text = cur.to_string();
+        let line = self.tokens[i].span.start.line;
+        let before = self.verbatim.len();
+        let after_brace = prev.is_some_and(|p| is_op(&p.kind, Operator::LeftBrace));
+        // Comments just above a `}` belong to the block it closes.
+        let depth = if is_op(cur, Operator::RightBrace) { self.depth + 1 } else { self.depth };
+        let last = self.verbatim_before(line, prev.map(|p| p.span.end.line),
+                                        after_brace, depth);
+        let after_directive = self.verbatim.len() < before;
+        if self.line_start {
+            if let Some(last) = last {
+                if line > last + 1
+                        && (after_directive
+                            || !prev.is_some_and(|p| is_op(&p.kind, Operator::LeftBrace)))
+                        && !is_op(cur, Operator::RightBrace) {
+                    self.out.push('\n');
+                }
+            }
+            self.indent(self.depth);
+        } else if prev.is_some_and(|p| self.space_before(&p.kind, cur)) {
+            // Arguments that would run past the width limit continue on
+            // the next line, one level deeper.
+            if self.parens > 0 && prev.is_some_and(|p| is_op(&p.kind, Operator::Comma))
+                    && self.line_len() + 1 + text.len() > self.config.max_width {
+                self.out.push('\n');
+                self.indent(self.depth + 1);
+            } else {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(&text);
+        self.line_start = false;
+        self.unary = is_unary(prev.map(|p| &p.kind), cur);
+        self.trailing_after(i);
+    }
+
+    fn format(mut self) -> String {
+        for i in 0..self.tokens.len() {
+            let cur = &self.tokens[i].kind;
+            let prev = i.checked_sub(1).map(|p| &self.tokens[p].kind);
+            let next = self.tokens.get(i + 1).map(|t| &t.kind);
+            match cur {
+                TokenKind::Operator(Operator::LeftBrace) => {
+                    if self.config.brace_style == BraceStyle::NextLine {
+                        self.newline();
+                    }
+                    let empty = self.is_empty_block(i);
+                    self.write(i);
+                    let is_do = prev.is_some_and(|p| is_keyword(p, Keyword::Do));
+                    self.blocks.push(is_do);
+                    if empty {
+                        self.empty_block = true;
+                        continue;
+                    }
+                    self.depth += 1;
+                    self.newline();
+                }
+                TokenKind::Operator(Operator::RightBrace) => {
+                    if std::mem::take(&mut self.empty_block) {
+                        self.out.push('}');
+                        self.trailing_after(i);
+                    } else {
+                        self.depth = self.depth.saturating_sub(1);
+                        self.newline();
+                        self.write(i);
+                    }
+                    let is_do = self.blocks.pop().unwrap_or(false);
+                    let same_line = self.config.brace_style == BraceStyle::SameLine;
+                    let joined = next.is_some_and(|n|
+                        (same_line && is_keyword(n, Keyword::Else))
+                        || is_op(n, Operator::Semicolon)
+                        || (is_do && is_keyword(n, Keyword::While)));
+                    if !joined {
+                        self.newline();
+                    }
+                }
+                TokenKind::Operator(Operator::LeftParen) => {
+                    self.write(i);
+                    self.parens += 1;
+                }
+                TokenKind::Operator(Operator::RightParen) => {
+                    self.write(i);
+                    self.parens = self.parens.saturating_sub(1);
+                }
+                TokenKind::Operator(Operator::Semicolon) => {
+                    self.write(i);
+                    if self.parens == 0 {
+                        self.newline();
+                    }
+                }
+                _ => self.write(i),
+            }
+        }
+        self.newline();
+        let last = self.tokens.last().map(|t| t.span.end.line);
+        self.verbatim_before(usize::MAX, last, false, 0);
+        self.out
+    }
+}
+
+// Reprints `src` with canonical indentation, spacing and brace placement.
+// Blank lines between statements are kept, collapsed to at most one.
+// Preprocessor directives are kept as written, unindented. Comments on
+// lines of their own are indented with the code around them; the rest stay
+// after the token they follow. `src` is lexed in `dialect`.
+pub fn format_source(src: &str, config: &FmtConfig, dialect: LanguageDialect)
+        -> Result<String, Error> {
+    let line_of = |offset: usize| src[..offset].matches('\n').count() + 1;
+    // A `#` inside a comment does not start a directive.
+    let directive_lines: HashSet<usize> = preprocess::strip_comments(src)?.lines().enumerate()
+        .filter(|(_, line)| line.trim_start().starts_with('#'))
+        .map(|(i, _)| i + 1)
+        .collect();
+    let src_lines: Vec<&str> = src.lines().collect();
+
+    // Comments are blanked out of the code, keeping every other byte where
+    // it was so token positions match `src`.
+    let comments = preprocess::comments(src)?;
+    let mut code = src.as_bytes().to_vec();
+    for range in &comments {
+        for byte in &mut code[range.clone()] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    }
+    let code = String::from_utf8_lossy(&code).into_owned();
+    let mut verbatim = Vec::new();
+    let lines: Vec<&str> = code.lines().enumerate()
+        .map(|(i, line)| if directive_lines.contains(&(i + 1)) {
+            let text = src_lines[i].trim();
+            verbatim.push(Verbatim { line: i + 1, end_line: i + 1, text, indent: false });
+            ""
+        } else {
+            line
+        })
+        .collect();
+    let tokens = driver::check(&lines.join("\n"), dialect)?;
+
+    let mut trailing = Vec::new();
+    for range in comments {
+        let (line, end_line) = (line_of(range.start), line_of(range.end));
+        if directive_lines.contains(&line) {
+            // Kept as part of the directive's text, which is one line.
+            if end_line > line {
+                return Err(Error::Unsupported(
+                    "Formatting a comment that continues past a directive"));
+            }
+            continue;
+        }
+        let line_start = src[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let text = src[range.clone()].trim_end();
+        let col = range.start - line_start;
+        let before = tokens.iter().rposition(|t|
+            (t.span.start.line, t.span.start.col) < (line, col));
+        match before {
+            Some(token) if tokens[token].span.start.line == line =>
+                trailing.push((token, text)),
+            _ => verbatim.push(Verbatim { line, end_line, text, indent: true }),
+        }
+    }
+    verbatim.sort_by_key(|v| v.line);
+    Ok(timing::time("format", ||
+        Formatter::new(&tokens, &verbatim, &trailing, config).format()))
+}
+
+pub fn is_formatted(src: &str, config: &FmtConfig, dialect: LanguageDialect)
+        -> Result<bool, Error> {
+    Ok(format_source(src, config, dialect)? == src)
+}
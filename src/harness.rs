@@ -0,0 +1,133 @@
+use crate::driver::{self, Unit};
+use crate::error::Error;
+use crate::log;
+use crate::preprocess::{self, Options};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+// What a test program should do, read from `// expect: STATUS` and
+// `// stdout: LINE` comments. A program without them should exit with 0;
+// its output is only compared when some `stdout` lines are given.
+// `// args: ARG...` comments give the arguments it is run with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Expectation {
+    pub status: i32,
+    pub stdout: Option<String>,
+    pub args: Vec<String>,
+}
+
+impl Expectation {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut expected = Expectation::default();
+        // A source whose comments cannot be read fails to compile, which
+        // is reported instead.
+        for range in preprocess::comments(src).unwrap_or_default() {
+            let Some(comment) = src[range.clone()].strip_prefix("//") else {
+                continue;
+            };
+            let i = src[..range.start].matches('\n').count();
+            let comment = comment.trim_start();
+            if let Some(status) = comment.strip_prefix("expect:") {
+                expected.status = status.trim().parse().map_err(|_|
+                    format!("invalid exit status '{}' on line {}", status.trim(), i + 1))?;
+            } else if let Some(out) = comment.strip_prefix("stdout:") {
+                let stdout = expected.stdout.get_or_insert_with(String::new);
+                stdout.push_str(out.strip_prefix(' ').unwrap_or(out));
+                stdout.push('\n');
+            } else if let Some(args) = comment.strip_prefix("args:") {
+                expected.args.extend(args.split_whitespace().map(String::from));
+            }
+        }
+        Ok(expected)
+    }
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    Pass,
+    // The program ran but did not do what was expected.
+    Fail(String),
+    // The program could not be compiled or run. Tagged with its path.
+    Error(Error),
+}
+
+impl Outcome {
+    pub const fn passed(&self) -> bool {
+        matches!(self, Outcome::Pass)
+    }
+
+    // What went wrong, for a test that did not pass.
+    pub fn message(&self) -> Option<String> {
+        match self {
+            Outcome::Pass => None,
+            Outcome::Fail(msg) => Some(msg.clone()),
+            Outcome::Error(e) => Some(e.to_string().trim_end().to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+}
+
+// The test programs at `path`: the file itself, or every `.bb` file under
+// the directory.
+pub fn discover(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    driver::find_sources(path)
+}
+
+// Compiles and runs the program at `path` and compares what it does with
+// the expectations in its comments.
+pub fn run_test(path: &Path, options: &Options) -> Outcome {
+    log!(Verbose, "testing {}", path.display());
+    let src = match driver::read_source(path) {
+        Ok(src) => src,
+        Err(e) => return Outcome::Error(e),
+    };
+    let expected = match Expectation::parse(&src) {
+        Ok(expected) => expected,
+        Err(msg) => return Outcome::Fail(msg),
+    };
+    let mut stdout = Vec::new();
+    let status = driver::preprocess_units(vec![Unit::new(path, src)], options)
+        .and_then(|units| {
+            let env = driver::program_env(&units, &expected.args, false);
+            driver::run_with_stdout(&units, &env, &mut stdout)
+        });
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => return Outcome::Error(e.in_file(path)),
+    };
+    let stdout = String::from_utf8_lossy(&stdout);
+    if status != expected.status {
+        return Outcome::Fail(format!("expected exit status {}, got {status}",
+                                     expected.status));
+    }
+    match expected.stdout {
+        Some(out) if out != stdout =>
+            Outcome::Fail(format!("expected output:\n{out}got:\n{stdout}")),
+        _ => Outcome::Pass,
+    }
+}
+
+// Details of every failure followed by the pass/fail counts.
+pub fn summary(results: &[TestResult]) -> String {
+    let mut out = String::new();
+    let failed: Vec<_> = results.iter().filter(|r| !r.outcome.passed()).collect();
+    if !failed.is_empty() {
+        out.push_str("\nfailures:\n");
+        for result in &failed {
+            let _ = match &result.outcome {
+                Outcome::Fail(msg) => writeln!(out, "{}: {msg}", result.path.display()),
+                Outcome::Error(e) => write!(out, "{e}"),
+                Outcome::Pass => Ok(()),
+            };
+        }
+    }
+    let status = if failed.is_empty() { "ok" } else { "FAILED" };
+    let _ = writeln!(out, "\ntest result: {status}. {} passed; {} failed",
+                     results.len() - failed.len(), failed.len());
+    out
+}
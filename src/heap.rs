@@ -0,0 +1,190 @@
+use crate::error::Error;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+// Elements a program may have allocated at once before it is stopped.
+pub const DEFAULT_HEAP_LIMIT: usize = 1 << 24;
+
+// How a program's arrays are reclaimed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Memory {
+    // `delete` frees an array, and using it afterwards is an error.
+    #[default]
+    Manual,
+    // `delete` is ignored and arrays the program can no longer reach are
+    // reclaimed by `collect`, so nothing in use can be freed.
+    Collected,
+}
+
+impl FromStr for Memory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(Memory::Manual),
+            "gc" => Ok(Memory::Collected),
+            _ => Err(format!("unknown memory mode '{s}', expected manual or gc")),
+        }
+    }
+}
+
+// A handle to an array on the heap. Handles to freed arrays are detected
+// rather than reaching whatever array reused the slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ArrayRef {
+    index: u32,
+    generation: u32,
+}
+
+// What an array of `len` elements counts against the limit. Empty arrays
+// still take a slot, so they count as one element.
+fn cost(len: usize) -> usize {
+    len.max(1)
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    generation: u32,
+    data: Option<Vec<i32>>,
+}
+
+// Runtime storage for arrays whose length is only known once the program
+// runs. Elements are ints; chars and bools are stored widened. Every
+// access is bounds checked.
+#[derive(Debug)]
+pub struct Heap {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    used: usize,
+    limit: usize,
+    memory: Memory,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Heap::with_limit(DEFAULT_HEAP_LIMIT)
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Heap { slots: Vec::new(), free: Vec::new(), used: 0, limit, memory: Memory::Manual }
+    }
+
+    pub fn with_memory(mut self, memory: Memory) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    pub fn memory(&self) -> Memory {
+        self.memory
+    }
+
+    // A new zeroed array of `len` elements.
+    pub fn alloc(&mut self, len: i32) -> Result<ArrayRef, Error> {
+        let size = usize::try_from(len).map_err(|_| Error::InvalidArrayLength(len))?;
+        if self.used + cost(size) > self.limit {
+            return Err(Error::HeapExhausted(self.limit));
+        }
+        let index = match self.free.last() {
+            Some(&index) => index,
+            None => {
+                u32::try_from(self.slots.len()).map_err(|_| Error::HeapExhausted(self.limit))?
+            }
+        };
+        let data = Some(vec![0; size]);
+        match self.free.pop() {
+            Some(_) => self.slots[index as usize].data = data,
+            None => self.slots.push(Slot { generation: 0, data }),
+        }
+        self.used += cost(size);
+        Ok(ArrayRef { index, generation: self.slots[index as usize].generation })
+    }
+
+    fn array(&self, array: ArrayRef) -> Result<&Vec<i32>, Error> {
+        self.slots.get(array.index as usize)
+            .filter(|slot| slot.generation == array.generation)
+            .and_then(|slot| slot.data.as_ref())
+            .ok_or(Error::DanglingReference)
+    }
+
+    fn array_mut(&mut self, array: ArrayRef) -> Result<&mut Vec<i32>, Error> {
+        self.slots.get_mut(array.index as usize)
+            .filter(|slot| slot.generation == array.generation)
+            .and_then(|slot| slot.data.as_mut())
+            .ok_or(Error::DanglingReference)
+    }
+
+    pub fn len(&self, array: ArrayRef) -> Result<usize, Error> {
+        Ok(self.array(array)?.len())
+    }
+
+    pub fn load(&self, array: ArrayRef, index: i32) -> Result<i32, Error> {
+        let data = self.array(array)?;
+        usize::try_from(index).ok()
+            .and_then(|i| data.get(i).copied())
+            .ok_or(Error::IndexOutOfBounds(index, data.len()))
+    }
+
+    pub fn store(&mut self, array: ArrayRef, index: i32, value: i32)
+            -> Result<(), Error> {
+        let data = self.array_mut(array)?;
+        let len = data.len();
+        let slot = usize::try_from(index).ok()
+            .and_then(|i| data.get_mut(i))
+            .ok_or(Error::IndexOutOfBounds(index, len))?;
+        *slot = value;
+        Ok(())
+    }
+
+    // Frees `array`. Freeing it again, or using it afterwards, is an error.
+    pub fn free(&mut self, array: ArrayRef) -> Result<(), Error> {
+        let len = self.len(array)?;
+        let slot = &mut self.slots[array.index as usize];
+        slot.data = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(array.index);
+        self.used -= cost(len);
+        Ok(())
+    }
+
+    // Runs `delete` on `array`, which frees it unless memory is collected.
+    pub fn delete(&mut self, array: ArrayRef) -> Result<(), Error> {
+        match self.memory {
+            Memory::Manual => self.free(array),
+            Memory::Collected => self.len(array).map(|_| ()),
+        }
+    }
+
+    // Frees every array but `roots`, the ones the program can still reach,
+    // returning how many were freed.
+    pub fn collect(&mut self, roots: &[ArrayRef]) -> usize {
+        let garbage: Vec<_> = self.slots.iter().enumerate()
+            .filter(|(_, slot)| slot.data.is_some())
+            .map(|(index, slot)| ArrayRef { index: index as u32, generation: slot.generation })
+            .filter(|array| !roots.contains(array))
+            .collect();
+        for &array in &garbage {
+            let _ = self.free(array);
+        }
+        garbage.len()
+    }
+
+    // Elements in live arrays, with empty arrays counted as one.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    // Arrays not yet freed, which are leaks once a manually managed
+    // program exits.
+    pub fn live_arrays(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.data.is_some()).count()
+    }
+}
@@ -0,0 +1,235 @@
+use crate::builtins::Builtin;
+use crate::dialect::LanguageDialect;
+use crate::driver;
+use crate::error::Error;
+use crate::lexer::{Keyword, TokenKind};
+use crate::preprocess;
+use crate::span::{Pos, Span};
+use crate::symbols::{SymbolKind, SymbolTable};
+use crate::timing;
+use std::fmt::Write;
+use std::ops::Range;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Class {
+    Keyword,
+    Type,
+    Number,
+    Char,
+    Bool,
+    Operator,
+    Function,
+    Global,
+    Parameter,
+    Local,
+    Comment,
+    Directive,
+}
+
+impl Class {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::Type => "type",
+            Class::Number => "number",
+            Class::Char => "char",
+            Class::Bool => "bool",
+            Class::Operator => "operator",
+            Class::Function => "function",
+            Class::Global => "global",
+            Class::Parameter => "parameter",
+            Class::Local => "local",
+            Class::Comment => "comment",
+            Class::Directive => "directive",
+        }
+    }
+
+    // SGR parameters, or `None` to leave the text uncoloured.
+    const fn ansi(self) -> Option<&'static str> {
+        match self {
+            Class::Keyword => Some("35"),
+            Class::Type => Some("36"),
+            Class::Number | Class::Bool => Some("33"),
+            Class::Char => Some("32"),
+            Class::Function => Some("34"),
+            Class::Global => Some("1"),
+            Class::Parameter => Some("3"),
+            Class::Comment => Some("90"),
+            Class::Directive => Some("1;35"),
+            Class::Operator | Class::Local => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Ansi,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(Format::Ansi),
+            "html" => Ok(Format::Html),
+            _ => Err(format!("unknown format '{s}', expected ansi or html")),
+        }
+    }
+}
+
+fn keyword_class(kw: &Keyword) -> Class {
+    match kw {
+        Keyword::Void | Keyword::Bool | Keyword::Char | Keyword::Int
+        | Keyword::Auto => Class::Type,
+        _ => Class::Keyword,
+    }
+}
+
+// Length in bytes of the token starting at `rest`. Token spans cannot be
+// used since they do not cover two-character operators or char literals.
+fn token_len(kind: &TokenKind, rest: &str) -> usize {
+    match kind {
+        TokenKind::Int(_) =>
+            rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len()),
+        TokenKind::Char(_) => if rest[1..].starts_with('\\') { 4 } else { 3 },
+        kind => kind.to_string().len(),
+    }
+}
+
+// Every classified stretch of `src`, each within a single line and in
+// source order. Text outside them, such as whitespace, is plain.
+pub fn classify(src: &str, dialect: LanguageDialect) -> Result<Vec<(Span, Class)>, Error> {
+    let lines: Vec<&str> = src.split('\n').collect();
+    let mut line_starts = Vec::with_capacity(lines.len());
+    let mut offset = 0;
+    for line in &lines {
+        line_starts.push(offset);
+        offset += line.len() + 1;
+    }
+    let span = |line: usize, cols: Range<usize>| Span::new(
+        Pos { line, col: cols.start }, Pos { line, col: cols.end });
+
+    let mut spans = Vec::new();
+    let comments = preprocess::comments(src)?;
+    for range in &comments {
+        for (i, &start) in line_starts.iter().enumerate() {
+            let end = start + lines[i].len();
+            if start < range.end && range.start <= end {
+                let cols = range.start.max(start) - start..range.end.min(end) - start;
+                if !cols.is_empty() {
+                    spans.push((span(i + 1, cols), Class::Comment));
+                }
+            }
+        }
+    }
+
+    // Directives are classified whole and hidden from the lexer, which
+    // has no tokens for them.
+    let code: Vec<String> = preprocess::strip_comments(src)?.split('\n').enumerate().map(|(i, line)| {
+        let text = line.trim();
+        if !text.starts_with('#') {
+            return line.to_string();
+        }
+        let start = line.len() - line.trim_start().len();
+        spans.push((span(i + 1, start..start + text.len()), Class::Directive));
+        " ".repeat(line.len())
+    }).collect();
+
+    let tokens = driver::lex(&code.join("\n"), dialect)?;
+    let table = SymbolTable::new(tokens.clone());
+    for (i, token) in tokens.iter().enumerate() {
+        let Pos { line, mut col } = token.span.start;
+        if matches!(token.kind, TokenKind::Char(_)) {
+            // Char spans start inside the quotes.
+            col -= 1;
+        }
+        let len = token_len(&token.kind, &code[line - 1][col..]);
+        let class = match &token.kind {
+            TokenKind::Keyword(kw) => keyword_class(kw),
+            TokenKind::Operator(_) => Class::Operator,
+            TokenKind::Bool(_) => Class::Bool,
+            TokenKind::Int(_) => Class::Number,
+            TokenKind::Char(_) => Class::Char,
+            TokenKind::Id(name) => match table.resolve(i).map(|s| s.kind) {
+                Some(SymbolKind::Function) => Class::Function,
+                Some(SymbolKind::Global) => Class::Global,
+                Some(SymbolKind::Parameter) => Class::Parameter,
+                Some(SymbolKind::Local) => Class::Local,
+                None if Builtin::from_name(name).is_some() => Class::Function,
+                None => continue,
+            },
+            TokenKind::Newline | TokenKind::Unknown => continue,
+        };
+        spans.push((span(line, col..col + len), class));
+    }
+    spans.sort_by_key(|(span, _)| (span.start.line, span.start.col));
+    Ok(spans)
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn push_text(out: &mut String, text: &str, format: Format) {
+    match format {
+        Format::Ansi => out.push_str(text),
+        Format::Html => escape_html(out, text),
+    }
+}
+
+// `src` with ANSI colour escapes, or as an HTML `<pre>` block whose spans
+// have `bb-` classes, e.g. `<span class="bb-keyword">`.
+pub fn highlight(src: &str, format: Format, dialect: LanguageDialect)
+        -> Result<String, Error> {
+    let spans = classify(src, dialect)?;
+    Ok(timing::time("highlight", || {
+        let mut out = String::with_capacity(src.len() * 2);
+        if format == Format::Html {
+            out.push_str("<pre class=\"bearbones\"><code>");
+        }
+        let mut spans = spans.iter().peekable();
+        for (i, line) in src.split('\n').enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let mut col = 0;
+            while let Some((span, class)) =
+                    spans.next_if(|(span, _)| span.start.line == i + 1) {
+                // Overlaps, as with a comment inside a directive, keep the
+                // first class.
+                if span.start.col < col || span.end.col > line.len() {
+                    continue;
+                }
+                push_text(&mut out, &line[col..span.start.col], format);
+                let text = &line[span.start.col..span.end.col];
+                match (format, class.ansi()) {
+                    (Format::Ansi, Some(sgr)) =>
+                        { let _ = write!(out, "\x1b[{sgr}m{text}\x1b[0m"); }
+                    (Format::Ansi, None) => out.push_str(text),
+                    (Format::Html, _) => {
+                        let _ = write!(out, "<span class=\"bb-{}\">", class.name());
+                        escape_html(&mut out, text);
+                        out.push_str("</span>");
+                    }
+                }
+                col = span.end.col;
+            }
+            push_text(&mut out, &line[col..], format);
+        }
+        if format == Format::Html {
+            out.push_str("</code></pre>\n");
+        }
+        out
+    }))
+}
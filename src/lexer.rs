@@ -1,7 +1,12 @@
+use crate::dialect::LanguageDialect;
 use crate::error::Error;
+pub use crate::span::{Pos, Span, Spanned};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::Peekable;
+use core::str::CharIndices;
 use phf::phf_map;
-use std::iter::Peekable;
-use std::str::CharIndices;
 
 pub type Token = Spanned<TokenKind>;
 
@@ -22,6 +27,10 @@ pub enum Keyword {
     Continue,
     Break,
     Return,
+    New,
+    Delete,
+    Auto,
+    Import,
 }
 
 const ESC_CHAR: [char; 7] = ['n', 'r', 't', '\\', '0', '\'', '\"'];
@@ -44,16 +53,27 @@ static KEYWORDS_MAP: phf::Map<&'static str, TokenKind> = phf_map! {
     "return" => TokenKind::Keyword(Keyword::Return),
 };
 
+// Keywords only in the extended dialect.
+static EXTENDED_KEYWORDS_MAP: phf::Map<&'static str, TokenKind> = phf_map! {
+    "new" => TokenKind::Keyword(Keyword::New),
+    "delete" => TokenKind::Keyword(Keyword::Delete),
+    "auto" => TokenKind::Keyword(Keyword::Auto),
+    "import" => TokenKind::Keyword(Keyword::Import),
+};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operator {
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Semicolon,
     Colon,
     Dot,
+    At,
 
     Lt,
     Le,
@@ -85,27 +105,90 @@ pub enum TokenKind {
     Unknown,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Pos {
-    pub line: usize,
-    pub col: usize,
-}
-
-#[derive(Clone, Debug, PartialEq)]
-pub struct Span {
-    pub start: Pos,
-    pub end: Pos,
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Keyword::*;
+        let s = match self {
+            Const => "const",
+            Void => "void",
+            Bool => "bool",
+            True => "true",
+            False => "false",
+            Char => "char",
+            Int => "int",
+            If => "if",
+            Else => "else",
+            For => "for",
+            While => "while",
+            Do => "do",
+            Continue => "continue",
+            Break => "break",
+            Return => "return",
+            New => "new",
+            Delete => "delete",
+            Auto => "auto",
+            Import => "import",
+        };
+        write!(f, "{s}")
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Spanned<T> {
-    pub kind: T,
-    pub span: Span,
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Operator::*;
+        let s = match self {
+            LeftParen => "(",
+            RightParen => ")",
+            LeftBrace => "{",
+            RightBrace => "}",
+            LeftBracket => "[",
+            RightBracket => "]",
+            Comma => ",",
+            Semicolon => ";",
+            Colon => ":",
+            Dot => ".",
+            At => "@",
+            Lt => "<",
+            Le => "<=",
+            Gt => ">",
+            Ge => ">=",
+            Eq => "=",
+            Eqq => "==",
+            Neq => "!=",
+            Not => "!",
+            Add => "+",
+            Sub => "-",
+            Mul => "*",
+            Div => "/",
+            AddAssign => "+=",
+            SubAssign => "-=",
+            MulAssign => "*=",
+            DivAssign => "/=",
+        };
+        write!(f, "{s}")
+    }
 }
 
-impl<T> Spanned<T> {
-    pub fn new(kind: T, span: Span) -> Self {
-        Spanned { kind, span }
+// Renders a token the way it would be written in source.
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Keyword(k) => write!(f, "{k}"),
+            TokenKind::Operator(op) => write!(f, "{op}"),
+            TokenKind::Id(name) => write!(f, "{name}"),
+            TokenKind::Bool(b) => write!(f, "{b}"),
+            TokenKind::Int(n) => write!(f, "{n}"),
+            TokenKind::Char(c) => match c {
+                '\n' => write!(f, "'\\n'"),
+                '\r' => write!(f, "'\\r'"),
+                '\t' => write!(f, "'\\t'"),
+                '\\' => write!(f, "'\\\\'"),
+                '\'' => write!(f, "'\\''"),
+                _ => write!(f, "'{c}'"),
+            },
+            TokenKind::Newline => writeln!(f),
+            TokenKind::Unknown => write!(f, "?"),
+        }
     }
 }
 
@@ -129,19 +212,26 @@ pub struct Lexer<'a> {
     line: usize,
     col: usize,
     start: usize,
+    dialect: LanguageDialect,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             cursor: input.char_indices().peekable(),
-            tokens: Vec::with_capacity(input.len()),
+            tokens: Vec::new(),
             line: 1,
             col: 0,
             start: 0,
+            dialect: LanguageDialect::default(),
         }
     }
 
+    pub fn with_dialect(mut self, dialect: LanguageDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
     fn col(&mut self, start: usize) -> usize {
         self.col = start - self.start;
         self.col
@@ -166,10 +256,13 @@ impl<'a> Lexer<'a> {
             ')' => TokenKind::Operator(Operator::RightParen),
             '{' => TokenKind::Operator(Operator::LeftBrace),
             '}' => TokenKind::Operator(Operator::RightBrace),
+            '[' => TokenKind::Operator(Operator::LeftBracket),
+            ']' => TokenKind::Operator(Operator::RightBracket),
             ',' => TokenKind::Operator(Operator::Comma),
             ':' => TokenKind::Operator(Operator::Colon),
             ';' => TokenKind::Operator(Operator::Semicolon),
             '.' => TokenKind::Operator(Operator::Dot),
+            '@' => TokenKind::Operator(Operator::At),
             _ => unreachable!(),
         };
         let span = self.new_span(start, c.len_utf8());
@@ -228,7 +321,7 @@ impl<'a> Lexer<'a> {
             Ok(Token::new(TokenKind::Char(c),
                 self.new_span(start, c.len_utf8())))
         } else {
-            return Err(Error::CharNotTerminated(self.new_span(start, len)))
+            Err(Error::CharNotTerminated(self.new_span(start, len)))
         }
     }
 
@@ -278,12 +371,12 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn consume_int(&mut self, start: usize) {
+    fn consume_int(&mut self, start: usize) -> Result<(), Error> {
         let mut lexeme = String::new();
         while let Some((_, n)) = self.cursor.next_if(|x| x.1.is_ascii_digit()) {
             lexeme.push(n);
         }
-        if self.cursor.peek().map_or(false, |x| x.1 == '.') {
+        if self.cursor.peek().is_some_and(|x| x.1 == '.') {
             lexeme.push('.');
             let _ = self.cursor.next();
             while let Some((_, num)) =
@@ -291,9 +384,11 @@ impl<'a> Lexer<'a> {
                 lexeme.push(num);
             }
         }
-        let num = lexeme.parse::<i32>().expect("Unable to parse number.");
         let span = self.new_span(start, lexeme.len());
+        // Only integers are supported, and they must fit in an `int`.
+        let num = lexeme.parse::<i32>().map_err(|_| Error::InvalidNumber(span.clone()))?;
         self.tokens.push(Token::new(TokenKind::Int(num), span));
+        Ok(())
     }
 
     fn consume_id(&mut self, start: usize) {
@@ -305,8 +400,13 @@ impl<'a> Lexer<'a> {
         }
         let len = lexeme.len();
         let span = self.new_span(start, len);
+        let extended = match self.dialect {
+            LanguageDialect::Strict => None,
+            LanguageDialect::Extended => EXTENDED_KEYWORDS_MAP.get(lexeme.as_str()),
+        };
         let kind = KEYWORDS_MAP
                     .get(lexeme.as_str())
+                    .or(extended)
                     .cloned()
                     .unwrap_or(TokenKind::Id(lexeme));
         self.tokens.push(Token::new(kind, span));
@@ -321,7 +421,9 @@ impl<'a> Lexer<'a> {
     pub fn scanner(&mut self) -> Result<Vec<Token>, Error> {
         while let Some(&(start, c)) = self.cursor.peek() {
             match c {
-                '(' | ')' | '{' | '}' | ',' | ':' | ';' | '.'
+                '(' | ')' | '{' | '}' | '[' | ']' | ',' | ':' | ';' | '.'
+                => self.consume_single_token(),
+                '@' if self.dialect == LanguageDialect::Extended
                 => self.consume_single_token(),
                 '+' | '-' | '*' | '/' | '<' | '>' | '!' | '='
                 => self.consume_double_token(),
@@ -336,7 +438,7 @@ impl<'a> Lexer<'a> {
                     let token = self.consume_char()?;
                     self.tokens.push(token);
                 }
-                _ if c.is_ascii_digit() => self.consume_int(start),
+                _ if c.is_ascii_digit() => self.consume_int(start)?,
                 _ if c.is_ascii_alphanumeric() => self.consume_id(start),
                 _ => self.consume_unknown(start, c),
             }
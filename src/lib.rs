@@ -1,5 +1,47 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod ast;
+pub mod builtins;
+pub mod dialect;
+#[cfg(feature = "std")]
+pub mod doc;
+#[cfg(feature = "std")]
+pub mod driver;
 pub mod lexer;
+#[cfg(feature = "std")]
+pub mod log;
+#[cfg(feature = "lsp")]
+pub mod lsp;
 pub mod error;
+#[cfg(feature = "tui")]
+pub mod explore;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fmt;
+#[cfg(feature = "std")]
+pub mod harness;
+pub mod heap;
+#[cfg(feature = "std")]
+pub mod highlight;
 pub mod parser;
-pub mod types;
\ No newline at end of file
+#[cfg(feature = "std")]
+pub mod preprocess;
+#[cfg(feature = "std")]
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod project;
+#[cfg(feature = "std")]
+pub mod record;
+pub mod span;
+#[cfg(feature = "std")]
+pub mod symbols;
+#[cfg(feature = "std")]
+pub mod testgen;
+#[cfg(feature = "std")]
+pub mod timing;
+pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    // Status output a user of the CLI expects to see.
+    Info = 1,
+    // Which phases run and on what.
+    Verbose = 2,
+    // Details useful when debugging the compiler itself.
+    Debug = 3,
+}
+
+type Sink = Box<dyn Fn(Level, &str) + Send + Sync>;
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+// The most detailed level that is emitted, or `None` to silence all
+// non-diagnostic output. Defaults to `Info`.
+pub fn set_max_level(level: Option<Level>) {
+    MAX_LEVEL.store(level.map_or(0, |l| l as u8), Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+// Routes messages to `sink` instead of stdout/stderr, e.g. into an
+// embedder's own logger.
+pub fn set_sink(sink: impl Fn(Level, &str) + Send + Sync + 'static) {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(sink));
+}
+
+pub fn reset_sink() {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn log(level: Level, args: fmt::Arguments) {
+    // Subscribers do their own filtering, so see every message.
+    #[cfg(feature = "tracing")]
+    match level {
+        Level::Info => tracing::info!("{args}"),
+        Level::Verbose => tracing::debug!("{args}"),
+        Level::Debug => tracing::trace!("{args}"),
+    }
+    if !enabled(level) {
+        return;
+    }
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner());
+    match (sink.as_ref(), level) {
+        (Some(sink), _) => sink(level, &args.to_string()),
+        (None, Level::Info) => println!("{args}"),
+        (None, Level::Verbose) => eprintln!("[verbose] {args}"),
+        (None, Level::Debug) => eprintln!("[debug] {args}"),
+    }
+}
+
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::$level, format_args!($($arg)*))
+    };
+}
@@ -0,0 +1,329 @@
+use crate::builtins::Builtin;
+use crate::dialect::LanguageDialect;
+use crate::driver;
+use crate::error::Error;
+use crate::log;
+use crate::preprocess::preprocess;
+use crate::span::{Pos, Span};
+use crate::symbols::{Symbol, SymbolKind, SymbolTable};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentSymbol,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf,
+    Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentContentChangeEvent, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub type Result<T> =
+    std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+// Lexer positions are 1-based lines with byte columns; LSP positions are
+// 0-based lines with UTF-16 columns.
+pub fn to_position(text: &str, pos: &Pos) -> Position {
+    let line = text.split('\n').nth(pos.line.saturating_sub(1)).unwrap_or("");
+    let col = line.get(..pos.col.min(line.len()))
+                .map_or(0, |s| s.encode_utf16().count());
+    Position::new(pos.line.saturating_sub(1) as u32, col as u32)
+}
+
+pub fn from_position(text: &str, pos: Position) -> Pos {
+    let line_start = to_offset(text, Position::new(pos.line, 0));
+    Pos { line: pos.line as usize + 1, col: to_offset(text, pos) - line_start }
+}
+
+fn to_range(text: &str, span: &Span) -> Range {
+    Range::new(to_position(text, &span.start), to_position(text, &span.end))
+}
+
+// Byte offset of an LSP position, clamped to the end of its line.
+pub fn to_offset(text: &str, pos: Position) -> usize {
+    let mut offset = 0;
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if i == pos.line as usize {
+            let content = line.strip_suffix('\n').unwrap_or(line);
+            let mut units = 0;
+            for (byte, c) in content.char_indices() {
+                if units >= pos.character as usize {
+                    return offset + byte;
+                }
+                units += c.len_utf16();
+            }
+            return offset + content.len();
+        }
+        offset += line.len();
+    }
+    text.len()
+}
+
+fn end_position(text: &str) -> Position {
+    let line = text.split('\n').count() - 1;
+    let last = text.rsplit('\n').next().unwrap_or("");
+    Position::new(line as u32, last.encode_utf16().count() as u32)
+}
+
+pub fn apply_change(text: &mut String, change: TextDocumentContentChangeEvent) {
+    match change.range {
+        Some(range) => {
+            let start = to_offset(text, range.start);
+            let end = to_offset(text, range.end).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+        None => *text = change.text,
+    }
+}
+
+pub fn diagnostics(text: &str, dialect: LanguageDialect) -> Vec<Diagnostic> {
+    diagnostics_in(Path::new(""), text, dialect)
+}
+
+// Diagnostics for a document at `path`, which quoted includes are resolved
+// against. Errors inside an included file are reported at the document start.
+pub fn diagnostics_in(path: &Path, text: &str, dialect: LanguageDialect) -> Vec<Diagnostic> {
+    let result = preprocess(path, text, &[]).and_then(|out|
+        driver::check(&out.src, dialect).map_err(|e| out.map.map_error(e, path)));
+    let e = match result {
+        Ok(_) => return Vec::new(),
+        Err(Error::InFile(file, e)) if file == path => *e,
+        Err(e) => e,
+    };
+    let range = match e.span() {
+        _ if matches!(e, Error::InFile(..)) => Range::default(),
+        Some(span) => to_range(text, span),
+        None => Range::new(end_position(text), end_position(text)),
+    };
+    vec![Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("bearbones".into()),
+        message: e.to_string().trim_end().to_string(),
+        ..Diagnostic::default()
+    }]
+}
+
+fn symbol_table(text: &str, dialect: LanguageDialect) -> SymbolTable {
+    SymbolTable::new(driver::lex(text, dialect).unwrap_or_default())
+}
+
+pub fn definition(uri: &Url, text: &str, pos: Position,
+                  dialect: LanguageDialect) -> Option<Location> {
+    let table = symbol_table(text, dialect);
+    let symbol = table.symbol_at(&from_position(text, pos))?;
+    Some(Location::new(uri.clone(), to_range(text, &symbol.span)))
+}
+
+pub fn hover(text: &str, pos: Position, dialect: LanguageDialect) -> Option<Hover> {
+    let table = symbol_table(text, dialect);
+    let pos = from_position(text, pos);
+    // Calls to the standard library resolve to no declaration.
+    let signature = match table.symbol_at(&pos) {
+        Some(symbol) => symbol.signature(),
+        None => Builtin::from_name(&table.tokens()[table.token_at(&pos)?].id_name())?
+                    .signature(),
+    };
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```c\n{signature}\n```"),
+        }),
+        range: None,
+    })
+}
+
+#[allow(deprecated)]
+fn document_symbol(text: &str, symbol: &Symbol,
+                    children: Option<Vec<DocumentSymbol>>) -> DocumentSymbol {
+    let kind = match symbol.kind {
+        SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+        _ => lsp_types::SymbolKind::VARIABLE,
+    };
+    DocumentSymbol {
+        name: symbol.name.clone(),
+        detail: Some(symbol.signature()),
+        kind,
+        tags: None,
+        deprecated: None,
+        range: to_range(text, &symbol.span),
+        selection_range: to_range(text, &symbol.span),
+        children,
+    }
+}
+
+// Functions and globals, with each function's parameters and locals
+// nested under it.
+pub fn document_symbols(text: &str, dialect: LanguageDialect) -> Vec<DocumentSymbol> {
+    let table = symbol_table(text, dialect);
+    let symbols = table.symbols();
+    symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.owner.is_none())
+        .map(|(i, s)| {
+            let children = (s.kind == SymbolKind::Function).then(|| symbols
+                .iter()
+                .filter(|c| c.owner == Some(i))
+                .map(|c| document_symbol(text, c, None))
+                .collect());
+            document_symbol(text, s, children)
+        })
+        .collect()
+}
+
+// A notification's params, or `None` after logging why they are
+// malformed. Notifications have no reply to carry the error.
+fn notification_params<P: DeserializeOwned>(method: &str, params: serde_json::Value)
+        -> Option<P> {
+    match serde_json::from_value(params) {
+        Ok(params) => Some(params),
+        Err(e) => {
+            log!(Info, "ignoring {method} notification: {e}");
+            None
+        }
+    }
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    docs: HashMap<Url, String>,
+    dialect: LanguageDialect,
+}
+
+impl Server<'_> {
+    fn publish(&self, uri: Url, diagnostics: Vec<Diagnostic>,
+                version: Option<i32>) -> Result<()> {
+        let params = PublishDiagnosticsParams { uri, diagnostics, version };
+        let not = Notification::new(PublishDiagnostics::METHOD.into(), params);
+        self.connection.sender.send(not.into())?;
+        Ok(())
+    }
+
+    fn diagnostics(&self, uri: &Url, text: &str) -> Vec<Diagnostic> {
+        let path = uri.to_file_path().unwrap_or_else(|_| PathBuf::from(uri.path()));
+        diagnostics_in(&path, text, self.dialect)
+    }
+
+    fn text(&self, uri: &Url) -> &str {
+        self.docs.get(uri).map_or("", |text| text.as_str())
+    }
+
+    fn request(&self, req: Request) -> Result<Response> {
+        let result = match req.method.as_str() {
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams =
+                    serde_json::from_value(req.params)?;
+                let TextDocumentPositionParams { text_document, position } =
+                    params.text_document_position_params;
+                let text = self.text(&text_document.uri);
+                serde_json::to_value(definition(&text_document.uri, text, position, self.dialect)
+                    .map(GotoDefinitionResponse::Scalar))?
+            }
+            HoverRequest::METHOD => {
+                let params: HoverParams = serde_json::from_value(req.params)?;
+                let TextDocumentPositionParams { text_document, position } =
+                    params.text_document_position_params;
+                serde_json::to_value(hover(self.text(&text_document.uri), position,
+                                           self.dialect))?
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params: DocumentSymbolParams =
+                    serde_json::from_value(req.params)?;
+                let symbols = document_symbols(self.text(&params.text_document.uri),
+                                               self.dialect);
+                serde_json::to_value(DocumentSymbolResponse::Nested(symbols))?
+            }
+            _ => return Ok(Response::new_err(req.id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unhandled method {}", req.method))),
+        };
+        Ok(Response::new_ok(req.id, result))
+    }
+
+    fn notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params): Option<DidOpenTextDocumentParams> =
+                    notification_params(&not.method, not.params) else { return Ok(()) };
+                let doc = params.text_document;
+                let diags = self.diagnostics(&doc.uri, &doc.text);
+                self.docs.insert(doc.uri.clone(), doc.text);
+                self.publish(doc.uri, diags, Some(doc.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params): Option<DidChangeTextDocumentParams> =
+                    notification_params(&not.method, not.params) else { return Ok(()) };
+                let doc = params.text_document;
+                let text = self.docs.entry(doc.uri.clone()).or_default();
+                for change in params.content_changes {
+                    apply_change(text, change);
+                }
+                let diags = self.diagnostics(&doc.uri, self.text(&doc.uri));
+                self.publish(doc.uri, diags, Some(doc.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params): Option<DidCloseTextDocumentParams> =
+                    notification_params(&not.method, not.params) else { return Ok(()) };
+                let uri = params.text_document.uri;
+                self.docs.remove(&uri);
+                self.publish(uri, Vec::new(), None)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Serves an initialized connection until the client shuts it down,
+// reading every document in `dialect`.
+pub fn serve(connection: &Connection, dialect: LanguageDialect) -> Result<()> {
+    let mut server = Server { connection, docs: HashMap::new(), dialect };
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                // Reading the params is all that can fail, and the client
+                // is told rather than the server stopping.
+                let id = req.id.clone();
+                let resp = server.request(req).unwrap_or_else(|e|
+                    Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()));
+                connection.sender.send(resp.into())?;
+            }
+            Message::Notification(not) => server.notification(not)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn run(dialect: LanguageDialect) -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    serve(&connection, dialect)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
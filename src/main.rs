@@ -1,3 +1,575 @@
-fn main() {
-    println!("Hello, world!");
+use bearbones::dialect::LanguageDialect;
+use bearbones::doc;
+use bearbones::driver::{self, Artifact, Emit, Unit};
+use bearbones::error::Error;
+use bearbones::fmt::{self, FmtConfig};
+use bearbones::harness::{self, TestResult};
+use bearbones::heap::Memory;
+use bearbones::highlight::{self, Format};
+use bearbones::log::{self, Level};
+use bearbones::preprocess::Options;
+use bearbones::profile::Profiler;
+use bearbones::project::ProjectConfig;
+use bearbones::record::Record;
+use bearbones::testgen;
+use bearbones::timing::{self, CountingAlloc};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap::error::ErrorKind;
+use clap_complete::Shell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
+// Exit status for a check that ran but found problems, such as
+// `fmt --check`. Errors exit with their kind's status instead.
+const FAILURE: u8 = 1;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+// Prints `record` as one line of JSON on stdout.
+fn emit(record: &Record) {
+    if let Ok(line) = serde_json::to_string(record) {
+        println!("{line}");
+    }
+}
+
+fn report_error(e: &Error) {
+    if json() {
+        emit(&Record::diagnostic(e));
+    } else {
+        eprint!("{e}");
+    }
+}
+
+fn print_text(text: &str) {
+    if json() {
+        emit(&Record::Output { text: text.to_string() });
+    } else {
+        print!("{text}");
+    }
+}
+
+// `--emit=KIND` dumps to stdout, `--emit=KIND=PATH` writes to a file.
+#[derive(Clone)]
+struct EmitArg {
+    stage: Emit,
+    path: Option<PathBuf>,
+}
+
+fn parse_emit(arg: &str) -> Result<EmitArg, String> {
+    let (stage, path) = match arg.split_once('=') {
+        Some((stage, path)) => (stage, Some(PathBuf::from(path))),
+        None => (arg, None),
+    };
+    Ok(EmitArg { stage: stage.parse()?, path })
+}
+
+// `-D NAME` defines NAME as 1, `-D NAME=VALUE` as VALUE.
+fn parse_define(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg.split_once('=').unwrap_or((arg, "1"));
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("'{name}' is not a valid macro name"));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+#[derive(Parser)]
+#[command(name = "bearbones", version, about = "A tiny C compiler",
+    after_help = "Pass - as the input path to read the program from stdin. \
+                  Without inputs, the sources listed in the nearest \
+                  bearbones.toml are used.")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Show which phases run; repeat for debugging detail
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Print nothing but diagnostics
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Report time and peak memory for each compiler pass
+    #[arg(long, global = true)]
+    time_passes: bool,
+    /// Process up to N files at once; defaults to the number of cores
+    #[arg(short, long, global = true, value_name = "N")]
+    jobs: Option<usize>,
+    /// Add DIR to the #include search path
+    #[arg(short = 'I', global = true, value_name = "DIR")]
+    include: Vec<PathBuf>,
+    /// Define a macro, as 1 unless a VALUE is given
+    #[arg(short = 'D', global = true, value_name = "NAME[=VALUE]",
+          value_parser = parse_define)]
+    define: Vec<(String, String)>,
+    /// Print all output as JSON records, one per line
+    #[arg(long, global = true)]
+    json: bool,
+    /// Keep absolute input paths out of output, so it is byte-identical
+    /// across machines
+    #[arg(long, global = true)]
+    reproducible: bool,
+    /// Accept only the C subset (strict), or also experimental syntax
+    /// (extended); defaults to the project's dialect
+    #[arg(long, global = true, value_name = "DIALECT",
+          value_parser = str::parse::<LanguageDialect>)]
+    dialect: Option<LanguageDialect>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compile a program
+    Build {
+        inputs: Vec<PathBuf>,
+        /// Dump an intermediate stage (tokens, ast, ir, bytecode, asm)
+        /// instead of compiling, optionally as KIND=PATH
+        #[arg(long, value_name = "KIND[=PATH]", value_parser = parse_emit)]
+        emit: Vec<EmitArg>,
+        /// Write the output to PATH instead of next to the input
+        #[arg(short, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Rebuild whenever an input file changes
+        #[arg(long)]
+        watch: bool,
+        /// Write every intermediate stage of each input into DIR
+        #[arg(long, value_name = "DIR")]
+        dump_dir: Option<PathBuf>,
+    },
+    /// Compile and execute a program, exiting with its exit status
+    Run {
+        inputs: Vec<PathBuf>,
+        /// Arguments passed to the program
+        #[arg(last = true)]
+        args: Vec<String>,
+        /// Report time spent in each function after the program exits
+        #[arg(long)]
+        profile: bool,
+        /// Free arrays on `delete` (manual), or ignore `delete` and
+        /// garbage collect them (gc)
+        #[arg(long, value_name = "MODE", default_value = "manual",
+              value_parser = str::parse::<Memory>)]
+        memory: Memory,
+    },
+    /// Format source files in place, or print to stdout when reading stdin
+    Fmt {
+        inputs: Vec<PathBuf>,
+        /// Report unformatted files instead of rewriting them
+        #[arg(long)]
+        check: bool,
+        /// Use this config instead of the nearest .bbfmt.toml
+        #[arg(long, value_name = "PATH")]
+        config: Option<PathBuf>,
+    },
+    /// Print API documentation generated from `///` comments on functions
+    /// and globals
+    Doc {
+        inputs: Vec<PathBuf>,
+        /// Output Markdown or an HTML fragment
+        #[arg(long, value_name = "FORMAT", default_value = "markdown",
+              value_parser = str::parse::<doc::Format>)]
+        format: doc::Format,
+    },
+    /// Browse a program's declarations, tokens and IR next to its source
+    #[cfg(feature = "tui")]
+    Explore {
+        input: PathBuf,
+    },
+    /// Print source files with syntax highlighting
+    Highlight {
+        inputs: Vec<PathBuf>,
+        /// Output ANSI colour escapes or an HTML fragment
+        #[arg(long, value_name = "FORMAT", default_value = "ansi",
+              value_parser = str::parse::<Format>)]
+        format: Format,
+    },
+    /// Start a language server on stdin/stdout
+    #[cfg(feature = "lsp")]
+    Lsp,
+    /// Check a program for errors without compiling it, reporting
+    /// problems in every input file
+    Check {
+        inputs: Vec<PathBuf>,
+        /// Recheck whenever an input file changes
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Run test programs, comparing their exit status and output with
+    /// `// expect: STATUS` and `// stdout: LINE` comments
+    Test {
+        /// Test programs, or directories to search for .bb files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Run random programs on every backend, reporting any whose exit
+    /// status or output differ between them
+    Testgen {
+        /// Seed of the first program; the rest use the seeds after it
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Number of programs to run
+        #[arg(long, value_name = "N", default_value_t = 100)]
+        count: u64,
+        /// Print the program for --seed instead of running anything
+        #[arg(long)]
+        print: bool,
+    },
+    /// Print a completion script for SHELL to stdout
+    Completions {
+        shell: Shell,
+    },
+}
+
+// Directory to start looking for `.bbfmt.toml` from.
+fn fmt_dir(input: &Path) -> &Path {
+    match input.parent() {
+        Some(dir) if input != Path::new(driver::STDIN) => dir,
+        _ => Path::new("."),
+    }
+}
+
+// The dialect given on the command line, else the project's.
+fn resolve_dialect(dialect: Option<LanguageDialect>) -> Result<LanguageDialect, Error> {
+    match dialect {
+        Some(dialect) => Ok(dialect),
+        None => Ok(ProjectConfig::discover(Path::new("."))?
+                    .map(|project| project.dialect).unwrap_or_default()),
+    }
+}
+
+// The inputs to use, falling back to the project's sources, and the
+// preprocessor options: the project's, extended by the command line's.
+fn project(inputs: Vec<PathBuf>, include: &[PathBuf],
+           defines: &[(String, String)], dialect: Option<LanguageDialect>)
+        -> Result<(Vec<PathBuf>, Options), Error> {
+    let project = ProjectConfig::discover(Path::new("."))?;
+    let mut options = project.as_ref().map(ProjectConfig::options)
+                        .unwrap_or_default();
+    options.include_paths.extend_from_slice(include);
+    options.defines.extend_from_slice(defines);
+    options.dialect = dialect.unwrap_or(options.dialect);
+    if !inputs.is_empty() {
+        return Ok((inputs, options));
+    }
+    let Some(project) = project else {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument, format!(
+                "no inputs given and no {} found", ProjectConfig::FILE_NAME))
+            .exit()
+    };
+    let inputs = project.source_files()?;
+    if inputs.is_empty() {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument,
+                "the project lists no source files")
+            .exit()
+    }
+    Ok((inputs, options))
+}
+
+fn load(inputs: &[PathBuf], options: &Options) -> Result<Vec<Unit>, Error> {
+    driver::preprocess_units(driver::load_units(inputs)?, options)
+}
+
+fn build(inputs: &[PathBuf], options: &Options, emit: &[EmitArg],
+        output: Option<&Path>, dump_dir: Option<&Path>, reproducible: bool)
+        -> Result<u8, Error> {
+    let units = load(inputs, options)?;
+    if let Some(dir) = dump_dir {
+        driver::dump_stages(&units, dir)?;
+    }
+    if emit.is_empty() {
+        let output = output.map_or_else(|| driver::output_path(
+                        &inputs[0], Artifact::Executable.extension()),
+                        Path::to_path_buf);
+        driver::build(&units, &output)?;
+    }
+    for EmitArg { stage, path } in emit {
+        let out = driver::emit_units(&units, *stage, reproducible)?;
+        match path {
+            Some(path) => fs::write(path, out)
+                            .map_err(|e| Error::Io(path.clone(), e))?,
+            None => print_text(&out),
+        }
+    }
+    Ok(0)
+}
+
+fn check(inputs: &[PathBuf], options: &Options) -> Result<u8, Error> {
+    let errors = driver::check_all(&load(inputs, options)?);
+    for e in &errors {
+        report_error(e);
+    }
+    Ok(errors.first().map_or(0, |e| e.kind().exit_code()))
+}
+
+fn test(paths: &[PathBuf], options: &Options) -> Result<u8, Error> {
+    let mut results = Vec::new();
+    for path in paths {
+        for path in harness::discover(path)? {
+            let outcome = harness::run_test(&path, options);
+            if json() {
+                emit(&Record::Test {
+                    path: path.clone(),
+                    passed: outcome.passed(),
+                    message: outcome.message(),
+                });
+            } else {
+                let status = if outcome.passed() { "ok" } else { "FAILED" };
+                println!("test {} ... {status}", path.display());
+            }
+            results.push(TestResult { path, outcome });
+        }
+    }
+    let failed = results.iter().filter(|r| !r.outcome.passed()).count();
+    if json() {
+        emit(&Record::TestSummary { passed: results.len() - failed, failed });
+    } else {
+        print!("{}", harness::summary(&results));
+    }
+    Ok(if failed == 0 { 0 } else { FAILURE })
+}
+
+fn differential(first: u64, count: u64) -> Result<u8, Error> {
+    let config = testgen::Config::default();
+    let (mut skipped, mut diverged) = (0, 0);
+    for seed in (0..count).map(|i| first.wrapping_add(i)) {
+        bearbones::log!(Verbose, "running program {seed}");
+        let comparison = testgen::compare(&testgen::generate(seed, &config), &[])?;
+        if !comparison.compared() {
+            skipped += 1;
+        } else if comparison.diverges() {
+            print_text(&format!("seed {seed} diverges:\n{}", comparison.report()));
+            diverged += 1;
+        }
+    }
+    print_text(&format!("{} programs compared, {skipped} skipped, {diverged} diverged\n",
+                        count - skipped));
+    // Programs that fewer than two backends could run were not tested.
+    if count > 0 && skipped == count {
+        return Err(Error::Unsupported("Differential testing with fewer than two backends"));
+    }
+    Ok(if diverged == 0 { 0 } else { FAILURE })
+}
+
+fn modified_times(inputs: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    inputs.iter()
+        .map(|input| fs::metadata(input).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+// Reruns `compile` whenever an input's modification time changes, clearing
+// the screen before each run so only fresh diagnostics are shown.
+fn watch(inputs: &[PathBuf], compile: impl Fn() -> Result<u8, Error>)
+        -> ! {
+    if inputs.iter().any(|input| input == Path::new(driver::STDIN)) {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--watch cannot read from stdin")
+            .exit()
+    }
+    bearbones::log!(Verbose, "watching {} files", inputs.len());
+    let mut last = Vec::new();
+    loop {
+        let times = modified_times(inputs);
+        if times != last {
+            if !json() {
+                print!("\x1b[2J\x1b[H");
+                let _ = io::stdout().flush();
+            }
+            if let Err(e) = compile() {
+                report_error(&e);
+            }
+            let _ = io::stdout().flush();
+            last = times;
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+fn execute(command: Command, include: &[PathBuf], defines: &[(String, String)],
+           dialect: Option<LanguageDialect>, reproducible: bool) -> Result<u8, Error> {
+    match command {
+        Command::Build { inputs, mut emit, mut output, watch: watching,
+                         dump_dir } => {
+            if !emit.is_empty() {
+                if let Some(path) = output.take() {
+                    let mut unnamed = emit.iter_mut().filter(|e| e.path.is_none());
+                    match (unnamed.next(), unnamed.next()) {
+                        (Some(e), None) => e.path = Some(path),
+                        (Some(_), Some(_)) => Cli::command()
+                            .error(ErrorKind::ArgumentConflict,
+                                "-o needs a single --emit without an explicit path")
+                            .exit(),
+                        _ => {}
+                    }
+                }
+            }
+            let (output, dump_dir) = (output.as_deref(), dump_dir.as_deref());
+            let (inputs, options) = project(inputs, include, defines, dialect)?;
+            if watching {
+                watch(&inputs, || build(&inputs, &options, &emit, output, dump_dir,
+                                        reproducible));
+            }
+            build(&inputs, &options, &emit, output, dump_dir, reproducible)
+        }
+        Command::Run { inputs, args, profile, memory } => {
+            let (inputs, options) = project(inputs, include, defines, dialect)?;
+            let units = load(&inputs, &options)?;
+            let env = driver::program_env(&units, &args, reproducible).with_memory(memory);
+            let status = if profile {
+                let mut profiler = Profiler::new();
+                let status = driver::run_profiled(&units, &env, &mut profiler);
+                if !profiler.is_empty() && json() {
+                    print_text(&profiler.report());
+                } else if !profiler.is_empty() {
+                    eprint!("{}", profiler.report());
+                }
+                status?
+            } else {
+                driver::run(&units, &env)?
+            };
+            Ok(driver::exit_status(status))
+        }
+        Command::Fmt { inputs, check, config } => {
+            let mut unformatted = false;
+            let explicit = config.map(|path| FmtConfig::load(&path)).transpose()?;
+            let (inputs, options) = project(inputs, &[], &[], dialect)?;
+            let units = driver::load_units(&inputs)?;
+            for (input, unit) in inputs.iter().zip(units) {
+                let config = match &explicit {
+                    Some(config) => config.clone(),
+                    None => FmtConfig::discover(fmt_dir(input))?,
+                };
+                let formatted = fmt::format_source(&unit.src, &config, options.dialect)
+                                .map_err(|e| e.in_file(&unit.path))?;
+                bearbones::log!(Verbose, "formatting {}", unit.path.display());
+                if check {
+                    if formatted != unit.src {
+                        if json() {
+                            emit(&Record::Unformatted { path: unit.path.clone() });
+                        } else {
+                            println!("{} is not formatted", unit.path.display());
+                        }
+                        unformatted = true;
+                    }
+                } else if input == Path::new(driver::STDIN) {
+                    print_text(&formatted);
+                } else {
+                    fs::write(&unit.path, formatted)
+                        .map_err(|e| Error::Io(unit.path, e))?;
+                }
+            }
+            Ok(if unformatted { FAILURE } else { 0 })
+        }
+        Command::Doc { inputs, format } => {
+            let (inputs, options) = project(inputs, &[], &[], dialect)?;
+            for unit in driver::load_units(&inputs)? {
+                let title = unit.path.file_name()
+                    .map_or_else(|| unit.path.display().to_string(),
+                                 |name| name.to_string_lossy().into_owned());
+                let out = doc::document(&title, &unit.src, format, options.dialect)
+                            .map_err(|e| e.in_file(&unit.path))?;
+                print_text(&out);
+            }
+            Ok(0)
+        }
+        #[cfg(feature = "tui")]
+        Command::Explore { input } => {
+            let src = driver::read_source(&input)?;
+            bearbones::explore::run(&src, resolve_dialect(dialect)?).map_err(|e| e.in_file(&input))?;
+            Ok(0)
+        }
+        Command::Highlight { inputs, format } => {
+            let (inputs, options) = project(inputs, &[], &[], dialect)?;
+            for unit in driver::load_units(&inputs)? {
+                let out = highlight::highlight(&unit.src, format, options.dialect)
+                            .map_err(|e| e.in_file(&unit.path))?;
+                print_text(&out);
+            }
+            Ok(0)
+        }
+        #[cfg(feature = "lsp")]
+        Command::Lsp => match bearbones::lsp::run(resolve_dialect(dialect)?) {
+            Ok(()) => Ok(0),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                Ok(FAILURE)
+            }
+        },
+        Command::Check { inputs, watch: watching } => {
+            let (inputs, options) = project(inputs, include, defines, dialect)?;
+            if watching {
+                watch(&inputs, || check(&inputs, &options));
+            }
+            check(&inputs, &options)
+        }
+        Command::Test { paths } => {
+            let (paths, options) = project(paths, include, defines, dialect)?;
+            test(&paths, &options)
+        }
+        Command::Testgen { seed, print: true, .. } => {
+            print_text(&testgen::generate(seed, &testgen::Config::default()));
+            Ok(0)
+        }
+        Command::Testgen { seed, count, print: false } => differential(seed, count),
+        Command::Completions { shell } => {
+            let mut cli = Cli::command();
+            let name = cli.get_name().to_string();
+            // Generated into a buffer, since the generator panics if
+            // stdout is closed early, as when piped into `head`.
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut cli, name, &mut script);
+            if json() {
+                print_text(&String::from_utf8_lossy(&script));
+            } else {
+                let _ = io::stdout().write_all(&script);
+            }
+            Ok(0)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    log::set_max_level(match (cli.quiet, cli.verbose) {
+        (true, _) => None,
+        (false, 0) => Some(Level::Info),
+        (false, 1) => Some(Level::Verbose),
+        (false, _) => Some(Level::Debug),
+    });
+    timing::set_enabled(cli.time_passes);
+    driver::set_jobs(cli.jobs.unwrap_or(0));
+    JSON.store(cli.json, Ordering::Relaxed);
+    if cli.json {
+        log::set_sink(|level, message| emit(&Record::Log {
+            level,
+            message: message.to_string(),
+        }));
+    }
+    let code = execute(cli.command, &cli.include, &cli.define, cli.dialect,
+                       cli.reproducible).unwrap_or_else(|e| {
+        report_error(&e);
+        e.kind().exit_code()
+    });
+    if cli.time_passes && cli.json {
+        timing::take().iter().for_each(|pass| emit(&Record::pass(pass)));
+    } else if cli.time_passes {
+        eprint!("{}", timing::report(&timing::take()));
+    }
+    if cli.json {
+        emit(&Record::Exit { code });
+    }
+    ExitCode::from(code)
 }
@@ -0,0 +1,613 @@
+use crate::dialect::LanguageDialect;
+use crate::error::Error;
+use crate::log;
+use crate::span::{Pos, Span};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// Nested includes deeper than this are assumed to be unguarded recursion.
+const MAX_INCLUDE_DEPTH: usize = 64;
+// Bounds on macro expansion, so that pathological macros fail instead of
+// exhausting the stack or memory.
+const MAX_MACRO_DEPTH: usize = 256;
+const MAX_EXPANSION_LEN: usize = 1 << 20;
+// Bound on all the expansions in a translation unit, nested ones included,
+// since many lines that each expand within the limit above could still add
+// up to any size.
+const MAX_UNIT_EXPANSION_LEN: usize = 1 << 24;
+
+// A macro use on an output line: the columns of its expansion and of the
+// use it replaced.
+#[derive(Clone, Debug, PartialEq)]
+struct Expansion {
+    line: usize,
+    out: Range<usize>,
+    src: Range<usize>,
+}
+
+// Maps lines of preprocessed output back to the file and line they came
+// from. An empty map is the identity: output lines are source lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    files: Vec<PathBuf>,
+    // Per output line: index into `files` and the original line number.
+    lines: Vec<(usize, usize)>,
+    // Sorted by line.
+    expansions: Vec<Expansion>,
+}
+
+impl SourceMap {
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn add_file(&mut self, path: &Path) -> usize {
+        match self.files.iter().position(|f| f == path) {
+            Some(id) => id,
+            None => {
+                self.files.push(path.to_path_buf());
+                self.files.len() - 1
+            }
+        }
+    }
+
+    // Column in the source of output column `col`. Anything inside a macro
+    // expansion maps to the use of the macro.
+    fn column(&self, line: usize, col: usize, end: bool) -> usize {
+        let first = self.expansions.partition_point(|e| e.line < line);
+        let mut shift = 0;
+        for exp in self.expansions[first..].iter().take_while(|e| e.line == line) {
+            if end && exp.out.start < col && col <= exp.out.end {
+                return exp.src.end;
+            }
+            if !end && exp.out.contains(&col) {
+                return exp.src.start;
+            }
+            if col < exp.out.end || (end && col == exp.out.start) {
+                break;
+            }
+            shift += exp.src.len() as isize - exp.out.len() as isize;
+        }
+        col.saturating_add_signed(shift)
+    }
+
+    fn locate_at(&self, pos: &Pos, end: bool) -> Option<(&Path, Pos)> {
+        let index = pos.line.checked_sub(1)?;
+        let &(file, line) = self.lines.get(index)?;
+        let col = self.column(index, pos.col, end);
+        Some((&self.files[file], Pos { line, col }))
+    }
+
+    // The originating file and position of a position in the output.
+    pub fn locate(&self, pos: &Pos) -> Option<(&Path, Pos)> {
+        self.locate_at(pos, false)
+    }
+
+    // Moves the error's span back into its originating file and tags the
+    // error with that file, falling back to `path` when it has no span.
+    pub fn map_error(&self, mut e: Error, path: &Path) -> Error {
+        if let Error::InFile(..) = e {
+            return e;
+        }
+        let located = e.span().and_then(|span| {
+            let (file, start) = self.locate(&span.start)?;
+            let end = match self.locate_at(&span.end, true) {
+                Some((end_file, end)) if end_file == file => end,
+                _ => start.clone(),
+            };
+            Some((file.to_path_buf(), Span::new(start, end)))
+        });
+        match (located, e.span_mut()) {
+            (Some((file, located)), Some(span)) => {
+                *span = located;
+                Error::InFile(file, Box::new(e))
+            }
+            _ => e.in_file(path),
+        }
+    }
+}
+
+// Settings shared by every unit of a build.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    pub include_paths: Vec<PathBuf>,
+    // Object-like macros defined before the first line, as by `-D`.
+    pub defines: Vec<(String, String)>,
+    pub dialect: LanguageDialect,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preprocessed {
+    pub src: String,
+    pub map: SourceMap,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Macro {
+    Object(String),
+    // Parameter names and body.
+    Function(Vec<String>, String),
+}
+
+struct Conditional {
+    // Whether the enclosing region is being kept.
+    parent: bool,
+    taken: bool,
+    in_else: bool,
+    span: Span,
+}
+
+impl Conditional {
+    const fn active(&self) -> bool {
+        self.parent && (self.taken != self.in_else)
+    }
+}
+
+pub struct Preprocessor<'a> {
+    include_paths: &'a [PathBuf],
+    // Whether `#include` may read files at all.
+    includes: bool,
+    defines: HashMap<String, Macro>,
+    // Files that asked to be included only once, by canonical path.
+    once: HashSet<PathBuf>,
+    out: String,
+    map: SourceMap,
+    // Bytes produced by expansions so far.
+    expanded: Cell<usize>,
+}
+
+fn directive_span(line_no: usize, line: &str) -> Span {
+    let col = line.len() - line.trim_start().len();
+    Span::new(Pos { line: line_no, col },
+              Pos { line: line_no, col: line.trim_end().len() })
+}
+
+fn identifier(s: &str) -> Option<&str> {
+    let end = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(s.len());
+    let id = &s[..end];
+    id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_').then_some(id)
+}
+
+fn is_identifier(tok: &str) -> bool {
+    identifier(tok).is_some_and(|id| id.len() == tok.len())
+}
+
+// Splits `text` into preprocessing tokens: identifiers and numbers, char
+// and string literals, runs of whitespace and single other characters.
+fn pp_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        if c.is_ascii_alphanumeric() || c == '_' || c.is_whitespace() {
+            let word = c.is_whitespace();
+            while let Some((i, d)) = chars.next_if(|&(_, d)| if word {
+                d.is_whitespace()
+            } else {
+                d.is_ascii_alphanumeric() || d == '_'
+            }) {
+                end = i + d.len_utf8();
+            }
+        } else if c == '\'' || c == '"' {
+            let mut escaped = false;
+            for (i, d) in chars.by_ref() {
+                end = i + d.len_utf8();
+                if escaped {
+                    escaped = false;
+                } else if d == '\\' {
+                    escaped = true;
+                } else if d == c {
+                    break;
+                }
+            }
+        }
+        tokens.push(&text[start..end]);
+    }
+    tokens
+}
+
+// Appends `text` after text it did not originally follow, separating the
+// two when they would otherwise lex as a single token.
+fn append_separated(out: &mut String, text: &str) {
+    const OPS: &str = "+-*/%<>=!&|^";
+    let joins = |a: char, b: char| {
+        let word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        (word(a) && word(b)) || (OPS.contains(a) && OPS.contains(b))
+    };
+    if let (Some(a), Some(b)) = (out.chars().last(), text.chars().next()) {
+        if joins(a, b) {
+            out.push(' ');
+        }
+    }
+    out.push_str(text);
+}
+
+// The arguments of a macro call whose `(` is `tokens[open]`, split at
+// top-level commas, and the index after the closing `)`.
+fn arguments<'t>(tokens: &[&'t str], open: usize)
+                -> Option<(Vec<Vec<&'t str>>, usize)> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+    for (i, &tok) in tokens.iter().enumerate().skip(open + 1) {
+        match tok {
+            ")" if depth == 0 => return Some((args, i + 1)),
+            "," if depth == 0 => {
+                args.push(Vec::new());
+                continue;
+            }
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => {}
+        }
+        args.last_mut()?.push(tok);
+    }
+    None
+}
+
+fn parse_define(rest: &str, span: &Span) -> Result<(String, Macro), Error> {
+    let invalid = || Error::InvalidDirective(span.clone());
+    let name = identifier(rest).ok_or_else(invalid)?;
+    let after = &rest[name.len()..];
+    // Only a `(` directly after the name makes a function-like macro.
+    let Some(after) = after.strip_prefix('(') else {
+        return Ok((name.to_string(), Macro::Object(after.trim().to_string())));
+    };
+    let (params, body) = after.split_once(')').ok_or_else(invalid)?;
+    let params: Vec<String> = match params.trim() {
+        "" => Vec::new(),
+        params => params.split(',')
+            .map(|p| Some(p.trim()).filter(|p| is_identifier(p))
+                        .map(str::to_string).ok_or_else(invalid))
+            .collect::<Result<_, _>>()?,
+    };
+    if (1..params.len()).any(|i| params[..i].contains(&params[i])) {
+        return Err(invalid());
+    }
+    Ok((name.to_string(), Macro::Function(params, body.trim().to_string())))
+}
+
+// Byte ranges of the comments in `src`. A line comment's range stops
+// before the newline ending it. Literals are skipped over.
+pub fn comments(src: &str) -> Result<Vec<Range<usize>>, Error> {
+    let mut ranges = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    let (mut line, mut line_start) = (1, 0);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '/' if chars.peek().is_some_and(|&(_, d)| d == '/') => {
+                while chars.next_if(|&(_, d)| d != '\n').is_some() {}
+                ranges.push(i..chars.peek().map_or(src.len(), |&(j, _)| j));
+            }
+            '/' if chars.peek().is_some_and(|&(_, d)| d == '*') => {
+                let start = Pos { line, col: i - line_start };
+                chars.next();
+                let mut end = None;
+                while let Some((j, d)) = chars.next() {
+                    if d == '*' && chars.next_if(|&(_, e)| e == '/').is_some() {
+                        end = Some(j + 2);
+                        break;
+                    }
+                    if d == '\n' {
+                        (line, line_start) = (line + 1, j + 1);
+                    }
+                }
+                let Some(end) = end else {
+                    let end = Pos { line: start.line, col: start.col + 2 };
+                    return Err(Error::UnterminatedComment(Span::new(start, end)));
+                };
+                ranges.push(i..end);
+            }
+            '\'' | '"' => {
+                let mut escaped = false;
+                while let Some((_, d)) = chars.next_if(|&(_, d)| d != '\n') {
+                    if escaped {
+                        escaped = false;
+                    } else if d == '\\' {
+                        escaped = true;
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            '\n' => (line, line_start) = (line + 1, i + 1),
+            _ => {}
+        }
+    }
+    Ok(ranges)
+}
+
+// Blanks out comments, keeping line breaks and the columns of the code
+// around them.
+pub fn strip_comments(src: &str) -> Result<String, Error> {
+    let mut out = String::with_capacity(src.len());
+    let mut last = 0;
+    for range in comments(src)? {
+        out.push_str(&src[last..range.start]);
+        // Line comments run to the end of the line, so are simply dropped.
+        if src[range.clone()].starts_with("/*") {
+            for c in src[range.clone()].chars() {
+                if c == '\n' {
+                    out.push('\n');
+                } else {
+                    out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+                }
+            }
+        }
+        last = range.end;
+    }
+    out.push_str(&src[last..]);
+    Ok(out)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(include_paths: &'a [PathBuf]) -> Self {
+        Preprocessor {
+            include_paths,
+            includes: true,
+            defines: HashMap::new(),
+            once: HashSet::new(),
+            out: String::new(),
+            map: SourceMap::default(),
+            expanded: Cell::new(0),
+        }
+    }
+
+    // Makes every `#include` fail, for input that must not touch the
+    // file system.
+    pub fn without_includes(mut self) -> Self {
+        self.includes = false;
+        self
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.defines.contains_key(name)
+    }
+
+    // `"file"` is looked up next to the including file first, `<file>`
+    // only on the include path.
+    fn resolve(&self, from: &Path, arg: &str, span: &Span)
+                -> Result<PathBuf, Error> {
+        let (name, local) = if let Some(name) = arg.strip_prefix('"')
+                                                    .and_then(|a| a.strip_suffix('"')) {
+            (name, true)
+        } else if let Some(name) = arg.strip_prefix('<')
+                                        .and_then(|a| a.strip_suffix('>')) {
+            (name, false)
+        } else {
+            return Err(Error::InvalidDirective(span.clone()));
+        };
+        if !self.includes {
+            return Err(Error::IncludeNotFound(span.clone(), name.to_string()));
+        }
+        let here = from.parent().unwrap_or(Path::new(""));
+        let local = local.then(|| here.join(name));
+        local.into_iter()
+            .chain(self.include_paths.iter().map(|dir| dir.join(name)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| Error::IncludeNotFound(span.clone(), name.to_string()))
+    }
+
+    fn include(&mut self, from: &Path, arg: &str, span: &Span, depth: usize)
+                -> Result<(), Error> {
+        if depth >= MAX_INCLUDE_DEPTH {
+            return Err(Error::IncludeTooDeep(span.clone()));
+        }
+        let path = self.resolve(from, arg, span)?;
+        if self.once.contains(&canonical(&path)) {
+            return Ok(());
+        }
+        log!(Debug, "including {}", path.display());
+        let src = fs::read_to_string(&path)
+                    .map_err(|e| Error::Io(path.clone(), e))?;
+        self.file(&path, &src, depth + 1)
+            .map_err(|e| e.in_file(&path))
+    }
+
+    fn directive(&mut self, path: &Path, text: &str, span: &Span,
+                conds: &mut Vec<Conditional>, depth: usize)
+                -> Result<(), Error> {
+        let text = text.trim_start();
+        let name = identifier(text).unwrap_or("");
+        let rest = text[name.len()..].trim();
+        let active = conds.last().is_none_or(Conditional::active);
+        match name {
+            "ifdef" | "ifndef" => {
+                let id = identifier(rest)
+                            .ok_or(Error::InvalidDirective(span.clone()))?;
+                let taken = self.is_defined(id) == (name == "ifdef");
+                conds.push(Conditional {
+                    parent: active, taken, in_else: false, span: span.clone(),
+                });
+            }
+            "else" => match conds.last_mut() {
+                Some(cond) if !cond.in_else => cond.in_else = true,
+                _ => return Err(Error::UnexpectedDirective(span.clone())),
+            },
+            "endif" => {
+                conds.pop().ok_or(Error::UnexpectedDirective(span.clone()))?;
+            }
+            _ if !active => {}
+            "include" => self.include(path, rest, span, depth)?,
+            "pragma" => {
+                if rest == "once" {
+                    self.once.insert(canonical(path));
+                }
+            }
+            "define" => {
+                let (name, mac) = parse_define(rest, span)?;
+                self.defines.insert(name, mac);
+            }
+            "undef" => {
+                let id = identifier(rest)
+                            .ok_or(Error::InvalidDirective(span.clone()))?;
+                self.defines.remove(id);
+            }
+            _ => return Err(Error::UnknownDirective(span.clone())),
+        }
+        Ok(())
+    }
+
+    pub fn define(&mut self, name: &str, mac: Macro) {
+        self.defines.insert(name.to_string(), mac);
+    }
+
+    // If `tokens[i]` uses a macro not in `hidden`, its expansion and the
+    // index after the use.
+    fn invocation(&self, tokens: &[&str], i: usize, hidden: &[&str],
+                  depth: usize, site: &Span)
+                -> Result<Option<(String, usize)>, Error> {
+        let name = tokens[i];
+        let Some(mac) = self.defines.get(name).filter(|_| !hidden.contains(&name))
+        else {
+            return Ok(None);
+        };
+        let mut inner = hidden.to_vec();
+        inner.push(name);
+        let (params, body) = match mac {
+            Macro::Object(body) =>
+                return Ok(Some((self.expand(body, &inner, depth + 1, site, None)?, i + 1))),
+            Macro::Function(params, body) => (params, body),
+        };
+        // A function-like macro name without arguments is left alone.
+        let open = (i + 1..tokens.len()).find(|&j| !tokens[j].trim().is_empty());
+        let Some(open) = open.filter(|&j| tokens[j] == "(") else {
+            return Ok(None);
+        };
+        let (mut args, end) = arguments(tokens, open).ok_or_else(||
+            Error::UnterminatedMacroCall(site.clone(), name.to_string()))?;
+        if params.is_empty() && args[0].iter().all(|t| t.trim().is_empty()) {
+            args.clear();
+        }
+        if args.len() != params.len() {
+            return Err(Error::MacroArgCount(site.clone(), name.to_string(),
+                                            params.len(), args.len()));
+        }
+        // Arguments are expanded before substitution, and the result is
+        // rescanned with this macro hidden so it cannot recurse.
+        let args = args.iter()
+            .map(|arg| self.expand(arg.concat().trim(), hidden, depth + 1, site, None))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut substituted = String::new();
+        for tok in pp_tokens(body) {
+            match params.iter().position(|p| p == tok) {
+                Some(k) => append_separated(&mut substituted, &args[k]),
+                None => append_separated(&mut substituted, tok),
+            }
+            if substituted.len() > MAX_EXPANSION_LEN {
+                return Err(Error::ExpansionTooLarge(site.clone()));
+            }
+        }
+        Ok(Some((self.expand(&substituted, &inner, depth + 1, site, None)?, end)))
+    }
+
+    // Expands the macros in `text`, except those in `hidden`. At the top
+    // level of a line, `uses` collects the output and source columns of
+    // each expansion, and errors point at the use instead of at `site`.
+    fn expand(&self, text: &str, hidden: &[&str], depth: usize, site: &Span,
+              mut uses: Option<&mut Vec<(Range<usize>, Range<usize>)>>)
+              -> Result<String, Error> {
+        if depth > MAX_MACRO_DEPTH {
+            return Err(Error::ExpansionTooLarge(site.clone()));
+        }
+        let tokens = pp_tokens(text);
+        let mut out = String::new();
+        let (mut i, mut offset, mut after_use) = (0, 0, false);
+        while i < tokens.len() {
+            let use_site = match uses {
+                Some(_) => Span::new(
+                    Pos { line: site.start.line, col: offset },
+                    Pos { line: site.start.line, col: offset + tokens[i].len() }),
+                None => site.clone(),
+            };
+            match self.invocation(&tokens, i, hidden, depth, &use_site)? {
+                Some((expansion, next)) => {
+                    self.expanded.set(self.expanded.get() + expansion.len());
+                    if self.expanded.get() > MAX_UNIT_EXPANSION_LEN {
+                        return Err(Error::ExpansionTooLarge(use_site));
+                    }
+                    let end = offset + tokens[i..next].concat().len();
+                    append_separated(&mut out, &expansion);
+                    if let Some(uses) = uses.as_deref_mut() {
+                        uses.push((out.len() - expansion.len()..out.len(), offset..end));
+                    }
+                    (i, offset, after_use) = (next, end, true);
+                    if out.len() > MAX_EXPANSION_LEN {
+                        return Err(Error::ExpansionTooLarge(use_site));
+                    }
+                }
+                None => {
+                    if after_use {
+                        append_separated(&mut out, tokens[i]);
+                    } else {
+                        out.push_str(tokens[i]);
+                    }
+                    (i, offset, after_use) = (i + 1, offset + tokens[i].len(), false);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn file(&mut self, path: &Path, src: &str, depth: usize)
+            -> Result<(), Error> {
+        let file = self.map.add_file(path);
+        let src = strip_comments(src)?;
+        let mut conds: Vec<Conditional> = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let line_no = i + 1;
+            if let Some(text) = line.trim_start().strip_prefix('#') {
+                let span = directive_span(line_no, line);
+                self.directive(path, text, &span, &mut conds, depth)?;
+                continue;
+            }
+            if conds.last().is_none_or(Conditional::active) {
+                let site = directive_span(line_no, line);
+                let mut uses = Vec::new();
+                let text = self.expand(line, &[], 0, &site, Some(&mut uses))?;
+                let index = self.map.lines.len();
+                self.map.expansions.extend(uses.into_iter()
+                    .map(|(out, src)| Expansion { line: index, out, src }));
+                self.out.push_str(&text);
+                self.out.push('\n');
+                self.map.lines.push((file, line_no));
+            }
+        }
+        match conds.pop() {
+            Some(cond) => Err(Error::UnterminatedConditional(cond.span)),
+            None => Ok(()),
+        }
+    }
+
+    // Expands `src`, read from `path`, into a single source text.
+    pub fn run(mut self, path: &Path, src: &str) -> Result<Preprocessed, Error> {
+        self.file(path, src, 0).map_err(|e| e.in_file(path))?;
+        // Keep a missing final newline missing, so errors at the end of
+        // input point where they did.
+        if !src.ends_with('\n') && self.out.ends_with('\n') {
+            self.out.pop();
+        }
+        Ok(Preprocessed { src: self.out, map: self.map })
+    }
+}
+
+pub fn preprocess(path: &Path, src: &str, include_paths: &[PathBuf])
+        -> Result<Preprocessed, Error> {
+    Preprocessor::new(include_paths).run(path, src)
+}
+
+pub fn preprocess_with(path: &Path, src: &str, options: &Options)
+        -> Result<Preprocessed, Error> {
+    let mut pp = Preprocessor::new(&options.include_paths);
+    for (name, value) in &options.defines {
+        pp.define(name, Macro::Object(value.clone()));
+    }
+    pp.run(path, src)
+}
@@ -0,0 +1,160 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+// One calling context: a function as reached through a particular chain
+// of callers. Node 0 is the root and stands for the program itself.
+#[derive(Clone, Debug)]
+struct Node {
+    name: String,
+    children: Vec<usize>,
+    calls: u64,
+    total: Duration,
+}
+
+impl Node {
+    fn new(name: &str) -> Self {
+        Node { name: name.to_string(), children: Vec::new(), calls: 0,
+               total: Duration::ZERO }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    // Time spent in the function's own code, excluding its callees.
+    pub self_time: Duration,
+    // Time from entry to exit, counting recursive activations once.
+    pub total_time: Duration,
+}
+
+// Builds a call tree from the enter and exit events of a running program.
+// Timestamps are passed in, measured from any fixed start, so the caller
+// chooses the clock.
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    nodes: Vec<Node>,
+    // Open activations: node and the time it was entered.
+    stack: Vec<(usize, Duration)>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler { nodes: vec![Node::new("")], stack: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    fn current(&self) -> usize {
+        self.stack.last().map_or(0, |&(node, _)| node)
+    }
+
+    pub fn enter(&mut self, name: &str, now: Duration) {
+        let parent = self.current();
+        let existing = self.nodes[parent].children.iter()
+                        .copied().find(|&c| self.nodes[c].name == name);
+        let node = existing.unwrap_or_else(|| {
+            self.nodes.push(Node::new(name));
+            let node = self.nodes.len() - 1;
+            self.nodes[parent].children.push(node);
+            node
+        });
+        self.nodes[node].calls += 1;
+        self.stack.push((node, now));
+    }
+
+    // Closes the innermost open function. Unbalanced exits are ignored.
+    pub fn exit(&mut self, now: Duration) {
+        if let Some((node, start)) = self.stack.pop() {
+            self.nodes[node].total += now.saturating_sub(start);
+        }
+    }
+
+    // Closes every function still open, as when the program exits early.
+    pub fn finish(&mut self, now: Duration) {
+        while !self.stack.is_empty() {
+            self.exit(now);
+        }
+    }
+
+    fn self_time(&self, node: usize) -> Duration {
+        let children = self.nodes[node].children.iter()
+                        .map(|&c| self.nodes[c].total).sum();
+        self.nodes[node].total.saturating_sub(children)
+    }
+
+    fn collect(&self, node: usize, path: &mut Vec<usize>,
+               flat: &mut Vec<FunctionProfile>) {
+        let n = &self.nodes[node];
+        let recursive = path.iter().any(|&p| self.nodes[p].name == n.name);
+        let i = match flat.iter().position(|f| f.name == n.name) {
+            Some(i) => i,
+            None => {
+                flat.push(FunctionProfile {
+                    name: n.name.clone(), calls: 0,
+                    self_time: Duration::ZERO, total_time: Duration::ZERO,
+                });
+                flat.len() - 1
+            }
+        };
+        flat[i].calls += n.calls;
+        flat[i].self_time += self.self_time(node);
+        if !recursive {
+            flat[i].total_time += n.total;
+        }
+        path.push(node);
+        for &child in &n.children {
+            self.collect(child, path, flat);
+        }
+        path.pop();
+    }
+
+    // Per-function totals, the most expensive own time first.
+    pub fn flat(&self) -> Vec<FunctionProfile> {
+        let mut flat = Vec::new();
+        let mut path = Vec::new();
+        for &child in &self.nodes[0].children {
+            self.collect(child, &mut path, &mut flat);
+        }
+        flat.sort_by(|a, b| b.self_time.cmp(&a.self_time)
+                        .then_with(|| a.name.cmp(&b.name)));
+        flat
+    }
+
+    fn tree(&self, node: usize, depth: usize, out: &mut String) {
+        let n = &self.nodes[node];
+        let _ = writeln!(out, "{:indent$}{} {} ({} calls, {} self)", "", n.name,
+                         ms(n.total), n.calls, ms(self.self_time(node)),
+                         indent = depth * 2);
+        for &child in &n.children {
+            self.tree(child, depth + 1, out);
+        }
+    }
+
+    // Flat profile followed by the call tree.
+    pub fn report(&self) -> String {
+        let mut out = format!("{:>12} {:>12} {:>8}  {}\n",
+                              "self", "total", "calls", "function");
+        for f in self.flat() {
+            let _ = writeln!(out, "{:>12} {:>12} {:>8}  {}", ms(f.self_time),
+                             ms(f.total_time), f.calls, f.name);
+        }
+        out.push_str("\ncall tree:\n");
+        for &child in &self.nodes[0].children {
+            self.tree(child, 1, &mut out);
+        }
+        out
+    }
+}
@@ -0,0 +1,104 @@
+use crate::dialect::LanguageDialect;
+use crate::driver;
+use crate::error::Error;
+use crate::preprocess::Options;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningLevel {
+    None,
+    #[default]
+    Default,
+    All,
+    // Warnings fail the build.
+    Error,
+}
+
+// Machines code can be generated for. Unknown targets are rejected when
+// the config is loaded rather than when code generation reaches them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    X86_64,
+    Aarch64,
+}
+
+// A project's `bearbones.toml`. Relative paths in it are relative to the
+// directory holding the file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    // Files and directories of `.bb` files to compile.
+    pub sources: Vec<PathBuf>,
+    pub include: Vec<PathBuf>,
+    pub defines: BTreeMap<String, String>,
+    pub warnings: WarningLevel,
+    // The host when unset.
+    pub target: Option<Target>,
+    pub dialect: LanguageDialect,
+}
+
+impl ProjectConfig {
+    pub const FILE_NAME: &'static str = "bearbones.toml";
+
+    pub fn from_toml(src: &str, path: &Path) -> Result<Self, Error> {
+        let mut config: Self = toml::from_str(src)
+            .map_err(|e| Error::InvalidConfig(path.to_path_buf(), e.to_string()))?;
+        if let Some(name) = config.defines.keys().find(|name| !is_identifier(name)) {
+            return Err(Error::InvalidConfig(path.to_path_buf(),
+                format!("'{name}' is not a valid macro name")));
+        }
+        let root = path.parent().unwrap_or(Path::new(""));
+        for path in config.sources.iter_mut().chain(&mut config.include) {
+            *path = root.join(&*path);
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let src = fs::read_to_string(path)
+                    .map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        Self::from_toml(&src, path)
+    }
+
+    // The nearest `bearbones.toml` in `dir` or its ancestors, if any.
+    pub fn discover(dir: &Path) -> Result<Option<Self>, Error> {
+        // Made absolute first, or `.` would have no ancestors to search.
+        let dir = std::path::absolute(dir).map_err(|e| Error::Io(dir.to_path_buf(), e))?;
+        for dir in dir.ancestors() {
+            let path = dir.join(Self::FILE_NAME);
+            if path.is_file() {
+                return Self::load(&path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    // Every source file, with directories expanded.
+    pub fn source_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        for source in &self.sources {
+            files.extend(driver::find_sources(source)?);
+        }
+        Ok(files)
+    }
+
+    pub fn options(&self) -> Options {
+        Options {
+            include_paths: self.include.clone(),
+            defines: self.defines.iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            dialect: self.dialect,
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
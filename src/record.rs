@@ -0,0 +1,71 @@
+use crate::error::{Error, ErrorKind};
+use crate::log::Level;
+use crate::span::Span;
+use crate::timing::PassTiming;
+use serde::Serialize;
+use std::path::PathBuf;
+
+// One line of machine-readable output, as printed by `--json`. Every
+// record is an object whose `type` field names its variant.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Diagnostic {
+        kind: ErrorKind,
+        file: Option<PathBuf>,
+        message: String,
+        span: Option<Span>,
+    },
+    Log {
+        level: Level,
+        message: String,
+    },
+    // Text a command would otherwise print, such as an emitted stage.
+    Output {
+        text: String,
+    },
+    Test {
+        path: PathBuf,
+        passed: bool,
+        message: Option<String>,
+    },
+    TestSummary {
+        passed: usize,
+        failed: usize,
+    },
+    // A file `fmt --check` would change.
+    Unformatted {
+        path: PathBuf,
+    },
+    Pass {
+        name: &'static str,
+        time_ms: f64,
+        peak_bytes: Option<usize>,
+    },
+    Exit {
+        code: u8,
+    },
+}
+
+impl Record {
+    pub fn diagnostic(e: &Error) -> Self {
+        let (file, inner) = match e {
+            Error::InFile(path, inner) => (Some(path.clone()), &**inner),
+            e => (None, e),
+        };
+        Record::Diagnostic {
+            kind: e.kind(),
+            file,
+            message: inner.to_string().trim_end().to_string(),
+            span: inner.span().cloned(),
+        }
+    }
+
+    pub fn pass(pass: &PassTiming) -> Self {
+        Record::Pass {
+            name: pass.name,
+            time_ms: pass.elapsed.as_secs_f64() * 1000.0,
+            peak_bytes: pass.peak_bytes,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Serialize;
+use core::fmt;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Pos {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+impl Span {
+    pub const fn new(start: Pos, end: Pos) -> Self {
+        Span { start, end }
+    }
+
+    // The smallest span covering both `self` and `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        let key = |p: &Pos| (p.line, p.col);
+        let start = if key(&self.start) <= key(&other.start) {
+            &self.start
+        } else {
+            &other.start
+        };
+        let end = if key(&self.end) >= key(&other.end) {
+            &self.end
+        } else {
+            &other.end
+        };
+        Span { start: start.clone(), end: end.clone() }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, columns {} - {}",
+            self.start.line, self.start.col, self.end.col
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub kind: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(kind: T, span: Span) -> Self {
+        Spanned { kind, span }
+    }
+
+    pub const fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub const fn kind(&self) -> &T {
+        &self.kind
+    }
+
+    pub fn into_inner(self) -> T {
+        self.kind
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned { kind: f(self.kind), span: self.span }
+    }
+
+    pub fn as_ref(&self) -> Spanned<&T> {
+        Spanned { kind: &self.kind, span: self.span.clone() }
+    }
+}
+
+impl<T> From<(T, Span)> for Spanned<T> {
+    fn from((kind, span): (T, Span)) -> Self {
+        Spanned { kind, span }
+    }
+}
+
+impl<T> From<Spanned<T>> for (T, Span) {
+    fn from(spanned: Spanned<T>) -> Self {
+        (spanned.kind, spanned.span)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::dialect::LanguageDialect;
+use crate::driver;
+use crate::error::Error;
+use crate::lexer::{Keyword, Operator, Token, TokenKind};
+use crate::preprocess;
+use crate::span::{Pos, Span};
+use crate::types::TypeKind;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Global,
+    Parameter,
+    Local,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub ty: TypeKind,
+    // Parameter types, for functions.
+    pub params: Vec<TypeKind>,
+    pub span: Span,
+    // The function a parameter or local belongs to, as a symbol index.
+    pub owner: Option<usize>,
+    // Token indices the name is visible in, start inclusive.
+    scope: (usize, usize),
+}
+
+impl Symbol {
+    pub fn signature(&self) -> String {
+        match self.kind {
+            SymbolKind::Function => {
+                let params: Vec<_> =
+                    self.params.iter().map(|p| p.to_string()).collect();
+                format!("{} {}({})", self.ty, self.name, params.join(", "))
+            }
+            _ => format!("{} {}", self.ty, self.name),
+        }
+    }
+}
+
+fn type_of(kind: &TokenKind) -> Option<TypeKind> {
+    match kind {
+        TokenKind::Keyword(Keyword::Void) => Some(TypeKind::Void),
+        TokenKind::Keyword(Keyword::Bool) => Some(TypeKind::Bool),
+        TokenKind::Keyword(Keyword::Char) => Some(TypeKind::Char),
+        TokenKind::Keyword(Keyword::Int) => Some(TypeKind::Int),
+        _ => None,
+    }
+}
+
+// Declarations and name resolution recovered from the token stream, using
+// braces for block scope. Serves position-based queries for tooling until
+// the parser and semantic analysis can answer them precisely.
+pub struct SymbolTable {
+    tokens: Vec<Token>,
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let mut table = SymbolTable { tokens, symbols: Vec::new() };
+        table.collect();
+        table
+    }
+
+    // Reads the declarations of unpreprocessed source, skipping comments
+    // and directive lines while keeping every position as written.
+    pub fn from_source(src: &str, dialect: LanguageDialect) -> Result<Self, Error> {
+        let stripped = preprocess::strip_comments(src)?;
+        let code: Vec<&str> = stripped.split('\n')
+            .map(|line| if line.trim_start().starts_with('#') { "" } else { line })
+            .collect();
+        Ok(Self::new(driver::lex(&code.join("\n"), dialect)?))
+    }
+
+    fn declare(&mut self, name: usize, ty: TypeKind, kind: SymbolKind,
+                owner: Option<usize>) -> usize {
+        let token = &self.tokens[name];
+        self.symbols.push(Symbol {
+            name: token.id_name(),
+            kind,
+            ty,
+            params: Vec::new(),
+            span: token.span.clone(),
+            owner,
+            scope: (name, self.tokens.len()),
+        });
+        self.symbols.len() - 1
+    }
+
+    fn collect(&mut self) {
+        // Symbols closed by each open brace.
+        let mut blocks: Vec<Vec<usize>> = Vec::new();
+        // Parameters waiting for the function body that scopes them.
+        let mut params: Vec<usize> = Vec::new();
+        let mut function: Option<usize> = None;
+        let mut in_params = false;
+        let mut parens: usize = 0;
+        // Type of the declaration statement being read, for `int a, b;`.
+        let mut decl: Option<TypeKind> = None;
+
+        for i in 0..self.tokens.len() {
+            let next = self.tokens.get(i + 1).map(|t| &t.kind);
+            match &self.tokens[i].kind {
+                TokenKind::Operator(Operator::LeftParen) => parens += 1,
+                TokenKind::Operator(Operator::RightParen) => {
+                    parens = parens.saturating_sub(1);
+                    if parens == 0 {
+                        in_params = false;
+                    }
+                }
+                TokenKind::Operator(Operator::LeftBrace) => {
+                    blocks.push(std::mem::take(&mut params));
+                    decl = None;
+                }
+                TokenKind::Operator(Operator::RightBrace) => {
+                    for s in blocks.pop().unwrap_or_default() {
+                        self.symbols[s].scope.1 = i + 1;
+                    }
+                    if blocks.is_empty() {
+                        function = None;
+                    }
+                }
+                TokenKind::Operator(Operator::Semicolon) if parens == 0 => {
+                    // A prototype's parameters are not visible past it.
+                    for p in params.drain(..) {
+                        self.symbols[p].scope.1 = i;
+                    }
+                    if blocks.is_empty() {
+                        function = None;
+                    }
+                    decl = None;
+                }
+                TokenKind::Operator(Operator::Comma)
+                        if parens == 0 && matches!(next, Some(TokenKind::Id(_))) => {
+                    if let Some(ty) = decl.clone() {
+                        self.declare_var(i + 1, ty, &mut blocks, function);
+                    }
+                }
+                kind => {
+                    let Some(ty) = type_of(kind) else { continue };
+                    if !matches!(next, Some(TokenKind::Id(_))) {
+                        continue;
+                    }
+                    let is_call = matches!(self.tokens.get(i + 2).map(|t| &t.kind),
+                                    Some(TokenKind::Operator(Operator::LeftParen)));
+                    if blocks.is_empty() && parens == 0 && is_call {
+                        let f = self.declare(i + 1, ty, SymbolKind::Function, None);
+                        function = Some(f);
+                        in_params = true;
+                    } else if in_params && parens == 1 {
+                        let p = self.declare(i + 1, ty.clone(),
+                                    SymbolKind::Parameter, function);
+                        if let Some(f) = function {
+                            self.symbols[f].params.push(ty);
+                        }
+                        params.push(p);
+                    } else if parens == 0 {
+                        self.declare_var(i + 1, ty.clone(), &mut blocks, function);
+                        decl = Some(ty);
+                    } else {
+                        // Declarations inside parens, as in `for (int i ...)`,
+                        // belong to the statement's block.
+                        let p = self.declare(i + 1, ty, SymbolKind::Local, function);
+                        params.push(p);
+                    }
+                }
+            }
+        }
+    }
+
+    fn declare_var(&mut self, name: usize, ty: TypeKind,
+                    blocks: &mut [Vec<usize>], function: Option<usize>) {
+        match blocks.last_mut() {
+            Some(block) => {
+                let s = self.declare(name, ty, SymbolKind::Local, function);
+                block.push(s);
+            }
+            None => {
+                self.declare(name, ty, SymbolKind::Global, None);
+            }
+        }
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    // Index of the token under `pos`, counting the column just past a
+    // token as part of it so a cursor at the end of a name still hits.
+    pub fn token_at(&self, pos: &Pos) -> Option<usize> {
+        self.tokens.iter().position(|t| {
+            let (start, end) = (&t.span.start, &t.span.end);
+            start.line == pos.line && start.col <= pos.col && pos.col <= end.col
+                && matches!(t.kind, TokenKind::Id(_))
+        })
+    }
+
+    // The declaration an identifier token refers to: the innermost visible
+    // one, which is always the most recently declared.
+    pub fn resolve(&self, token: usize) -> Option<&Symbol> {
+        let TokenKind::Id(name) = &self.tokens.get(token)?.kind else {
+            return None;
+        };
+        self.symbols
+            .iter()
+            .filter(|s| &s.name == name && s.scope.0 <= token && token < s.scope.1)
+            .max_by_key(|s| s.scope.0)
+    }
+
+    pub fn symbol_at(&self, pos: &Pos) -> Option<&Symbol> {
+        self.resolve(self.token_at(pos)?)
+    }
+}
@@ -0,0 +1,370 @@
+use crate::builtins::Builtin;
+use crate::driver::{self, Unit};
+use crate::error::Error;
+use crate::preprocess::Options;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Limits on the size of generated programs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    // Functions besides `main`.
+    pub functions: usize,
+    // Statements in each block, at most. Every block gets at least one.
+    pub statements: usize,
+    // How deeply blocks and expressions nest.
+    pub depth: usize,
+    // Iterations of each loop.
+    pub iterations: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { functions: 3, statements: 5, depth: 3, iterations: 4 }
+    }
+}
+
+// SplitMix64, so a seed gives the same program on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+struct Var {
+    name: String,
+    // Loop counters are only changed by their loop, so every loop ends.
+    counter: bool,
+}
+
+struct Generator {
+    rng: Rng,
+    config: Config,
+    out: String,
+    indent: usize,
+    globals: Vec<String>,
+    // Functions defined so far and their parameter counts. Calls only go
+    // to earlier functions, so there is no recursion.
+    functions: Vec<(String, usize)>,
+    scopes: Vec<Vec<Var>>,
+    next_id: usize,
+}
+
+impl Generator {
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id)
+    }
+
+    fn line(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{}{text}", "    ".repeat(self.indent));
+    }
+
+    fn vars(&self) -> impl Iterator<Item = &Var> {
+        self.scopes.iter().flatten()
+    }
+
+    fn literal(&mut self) -> String {
+        match self.rng.below(10) {
+            0 => (self.rng.next() as i32 / 2).to_string(),
+            1 => "0".to_string(),
+            _ => self.rng.below(100).to_string(),
+        }
+    }
+
+    // An `int` expression. Division is only by positive constants, so no
+    // program divides by zero.
+    fn expr(&mut self, depth: usize) -> String {
+        let names: Vec<String> = self.vars().map(|v| v.name.clone())
+            .chain(self.globals.iter().cloned()).collect();
+        if depth == 0 || self.rng.chance(25) {
+            return if !names.is_empty() && self.rng.chance(60) {
+                self.rng.pick(&names).clone()
+            } else {
+                self.literal()
+            };
+        }
+        match self.rng.below(7) {
+            0 => format!("-({})", self.expr(depth - 1)),
+            1 => format!("({}) / {}", self.expr(depth - 1), self.rng.below(9) + 1),
+            2 => {
+                let builtin = *self.rng.pick(&[Builtin::Abs, Builtin::Min, Builtin::Max]);
+                let args: Vec<String> = (0..builtin.params().len())
+                    .map(|_| self.expr(depth - 1)).collect();
+                format!("{}({})", builtin.name(), args.join(", "))
+            }
+            3 if !self.functions.is_empty() => {
+                let (name, params) = self.rng.pick(&self.functions).clone();
+                let args: Vec<String> = (0..params).map(|_| self.expr(depth - 1)).collect();
+                format!("{name}({})", args.join(", "))
+            }
+            _ => {
+                let op = self.rng.pick(&["+", "-", "*"]);
+                format!("{} {op} {}", self.expr(depth - 1), self.expr(depth - 1))
+            }
+        }
+    }
+
+    fn condition(&mut self, depth: usize) -> String {
+        match self.rng.below(8) {
+            0 => self.rng.pick(&["true", "false"]).to_string(),
+            1 if depth > 0 => format!("!({})", self.condition(depth - 1)),
+            _ => {
+                let op = self.rng.pick(&["<", "<=", ">", ">=", "==", "!="]);
+                format!("{} {op} {}", self.expr(depth), self.expr(depth))
+            }
+        }
+    }
+
+    fn block(&mut self, depth: usize) {
+        self.scopes.push(Vec::new());
+        self.indent += 1;
+        for _ in 0..self.rng.below(self.config.statements.max(1)) + 1 {
+            self.statement(depth);
+        }
+        self.indent -= 1;
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, depth: usize) {
+        let assignable: Vec<String> = self.vars().filter(|v| !v.counter)
+            .map(|v| v.name.clone()).chain(self.globals.iter().cloned()).collect();
+        let expr_depth = self.config.depth;
+        match self.rng.below(6) {
+            0 | 1 if !assignable.is_empty() => {
+                let name = self.rng.pick(&assignable).clone();
+                let op = self.rng.pick(&["=", "+=", "-=", "*="]);
+                let value = self.expr(expr_depth);
+                self.line(&format!("{name} {op} {value};"));
+            }
+            2 if depth > 0 => {
+                let cond = self.condition(expr_depth);
+                self.line(&format!("if ({cond}) {{"));
+                self.block(depth - 1);
+                if self.rng.chance(50) {
+                    self.line("} else {");
+                    self.block(depth - 1);
+                }
+                self.line("}");
+            }
+            3 if depth > 0 => {
+                let counter = self.fresh("i");
+                let limit = self.rng.below(self.config.iterations + 1);
+                self.line(&format!("for (int {counter} = 0; {counter} < {limit}; {counter} += 1) {{"));
+                self.scopes.push(vec![Var { name: counter, counter: true }]);
+                self.block(depth - 1);
+                self.scopes.pop();
+                self.line("}");
+            }
+            4 if depth > 0 => {
+                let counter = self.fresh("w");
+                let limit = self.rng.below(self.config.iterations + 1);
+                self.line(&format!("int {counter} = 0;"));
+                self.line(&format!("while ({counter} < {limit}) {{"));
+                self.scopes.last_mut().unwrap().push(Var { name: counter.clone(), counter: true });
+                self.block(depth - 1);
+                self.indent += 1;
+                self.line(&format!("{counter} += 1;"));
+                self.indent -= 1;
+                self.line("}");
+            }
+            _ => {
+                let name = self.fresh("v");
+                let value = self.expr(expr_depth);
+                self.line(&format!("int {name} = {value};"));
+                self.scopes.last_mut().unwrap().push(Var { name, counter: false });
+            }
+        }
+    }
+
+    // Like a block, but ending with a return of a value that may depend on
+    // anything declared in it.
+    fn body(&mut self, params: Vec<String>) {
+        self.scopes.push(params.into_iter().map(|name| Var { name, counter: false }).collect());
+        self.indent += 1;
+        for _ in 0..self.rng.below(self.config.statements.max(1)) + 1 {
+            self.statement(self.config.depth);
+        }
+        let value = self.expr(self.config.depth);
+        self.line(&format!("return {value};"));
+        self.indent -= 1;
+        self.scopes.pop();
+    }
+
+    fn program(&mut self, seed: u64) -> String {
+        let _ = writeln!(self.out, "// Generated by `bearbones testgen --seed {seed}`.");
+        for _ in 0..self.rng.below(3) {
+            let name = self.fresh("g");
+            let value = self.literal();
+            self.line(&format!("int {name} = {value};"));
+            self.globals.push(name);
+        }
+        for _ in 0..self.config.functions {
+            let name = self.fresh("f");
+            let params: Vec<String> = (0..self.rng.below(3)).map(|_| self.fresh("p")).collect();
+            let list: Vec<String> = params.iter().map(|p| format!("int {p}")).collect();
+            self.line("");
+            self.line(&format!("int {name}({}) {{", list.join(", ")));
+            self.body(params.clone());
+            self.line("}");
+            self.functions.push((name, params.len()));
+        }
+        self.line("");
+        self.line("int main() {");
+        self.body(Vec::new());
+        self.line("}");
+        std::mem::take(&mut self.out)
+    }
+}
+
+// A random well-typed program that always terminates, the same for the
+// same `seed` and `config`. Arithmetic may overflow, which wraps.
+pub fn generate(seed: u64, config: &Config) -> String {
+    let mut generator = Generator {
+        rng: Rng(seed),
+        config: config.clone(),
+        out: String::new(),
+        indent: 0,
+        globals: Vec::new(),
+        functions: Vec::new(),
+        scopes: Vec::new(),
+        next_id: 0,
+    };
+    generator.program(seed)
+}
+
+// Numbers the executables built by the native backend, which may run on
+// several threads at once.
+static NEXT_EXE: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Interpreter,
+    Vm,
+    Native,
+}
+
+// What a program visibly did.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    // As by `driver::exit_status`.
+    pub status: u8,
+    pub stdout: String,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::Interpreter, Backend::Vm, Backend::Native];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Backend::Interpreter => "interpreter",
+            Backend::Vm => "vm",
+            Backend::Native => "native",
+        }
+    }
+
+    pub fn run(self, units: &[Unit], args: &[String]) -> Result<Observation, Error> {
+        match self {
+            Backend::Interpreter => {
+                let mut stdout = Vec::new();
+                let env = driver::program_env(units, args, false);
+                let status = driver::run_with_stdout(units, &env, &mut stdout)?;
+                Ok(Observation {
+                    status: driver::exit_status(status),
+                    stdout: String::from_utf8_lossy(&stdout).into_owned(),
+                })
+            }
+            Backend::Vm => {
+                driver::check_units(units)?;
+                Err(Error::Unsupported("The bytecode VM"))
+            }
+            Backend::Native => {
+                let exe = std::env::temp_dir()
+                    .join(format!("bearbones-testgen-{}-{}", process::id(),
+                                  NEXT_EXE.fetch_add(1, Ordering::Relaxed)));
+                driver::build(units, &exe)?;
+                let output = process::Command::new(&exe).args(args).output();
+                let _ = std::fs::remove_file(&exe);
+                let output = output.map_err(|e| Error::Io(exe, e))?;
+                let status = output.status.code().ok_or_else(||
+                    Error::Internal("native program was killed by a signal".to_string()))?;
+                Ok(Observation {
+                    status: driver::exit_status(status),
+                    stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+                })
+            }
+        }
+    }
+}
+
+// What each backend made of one program.
+#[derive(Debug)]
+pub struct Comparison {
+    pub results: Vec<(Backend, Result<Observation, Error>)>,
+}
+
+impl Comparison {
+    // Results of the backends that could run the program. Errors are
+    // compared by their message, so backends agree when they fail alike.
+    fn ran(&self) -> impl Iterator<Item = Result<&Observation, String>> {
+        self.results.iter().filter_map(|(_, result)| match result {
+            Err(Error::Unsupported(_)) => None,
+            Ok(observation) => Some(Ok(observation)),
+            Err(e) => Some(Err(e.to_string())),
+        })
+    }
+
+    // Whether at least two backends ran the program, so there was
+    // anything to compare.
+    pub fn compared(&self) -> bool {
+        self.ran().nth(1).is_some()
+    }
+
+    pub fn diverges(&self) -> bool {
+        let mut ran = self.ran();
+        let Some(first) = ran.next() else { return false };
+        ran.any(|result| result != first)
+    }
+
+    // One line for each backend.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        for (backend, result) in &self.results {
+            let _ = match result {
+                Ok(o) => writeln!(out, "{}: exit {}, stdout {:?}", backend.name(),
+                                  o.status, o.stdout),
+                Err(e) => writeln!(out, "{}: {}", backend.name(), e.to_string().trim_end()),
+            };
+        }
+        out
+    }
+}
+
+// Preprocesses `src` and runs it on every backend.
+pub fn compare(src: &str, args: &[String]) -> Result<Comparison, Error> {
+    let unit = Unit::new(PathBuf::from("testgen.bb"), src);
+    let units = driver::preprocess_units(vec![unit], &Options::default())?;
+    Ok(Comparison {
+        results: Backend::ALL.iter().map(|&b| (b, b.run(&units, args))).collect(),
+    })
+}
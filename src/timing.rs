@@ -0,0 +1,131 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PASSES: Mutex<Vec<PassTiming>> = Mutex::new(Vec::new());
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+// Global allocator that tracks live and peak heap usage so passes can
+// report how much memory they needed. Binaries opt in with
+// `#[global_allocator] static A: CountingAlloc = CountingAlloc;`.
+pub struct CountingAlloc;
+
+fn grow(bytes: usize) {
+    let current = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+    INSTALLED.store(true, Ordering::Relaxed);
+}
+
+fn shrink(bytes: usize) {
+    CURRENT.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize)
+                -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                shrink(layout.size() - new_size);
+            }
+        }
+        new
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    // Peak heap growth during the pass, when `CountingAlloc` is installed.
+    pub peak_bytes: Option<usize>,
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Runs `pass`, recording how long it took when timing is enabled. With the
+// `tracing` feature, each pass also runs inside a `pass` span.
+pub fn time<T>(name: &'static str, pass: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("pass", name).entered();
+    if !enabled() {
+        return pass();
+    }
+    let base = CURRENT.load(Ordering::Relaxed);
+    let outer_peak = PEAK.swap(base, Ordering::Relaxed);
+    let start = Instant::now();
+    let result = pass();
+    let elapsed = start.elapsed();
+    let peak = PEAK.fetch_max(outer_peak, Ordering::Relaxed);
+    let peak_bytes = INSTALLED.load(Ordering::Relaxed)
+                        .then(|| peak.saturating_sub(base));
+    PASSES.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(PassTiming { name, elapsed, peak_bytes });
+    result
+}
+
+// Returns the passes recorded so far, clearing the record.
+pub fn take() -> Vec<PassTiming> {
+    std::mem::take(&mut *PASSES.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+fn format_bytes(bytes: Option<usize>) -> String {
+    match bytes {
+        None => "-".to_string(),
+        Some(b) if b < 1024 => format!("{b} B"),
+        Some(b) if b < 1024 * 1024 => format!("{:.1} KiB", b as f64 / 1024.0),
+        Some(b) => format!("{:.1} MiB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+// Table of passes in the order they first ran. Repeated passes, such as
+// lexing several files, are summed into one row.
+pub fn report(passes: &[PassTiming]) -> String {
+    let mut rows: Vec<(&str, usize, Duration, Option<usize>)> = Vec::new();
+    for pass in passes {
+        match rows.iter_mut().find(|row| row.0 == pass.name) {
+            Some(row) => {
+                row.1 += 1;
+                row.2 += pass.elapsed;
+                row.3 = row.3.max(pass.peak_bytes);
+            }
+            None => rows.push((pass.name, 1, pass.elapsed, pass.peak_bytes)),
+        }
+    }
+    let mut out = format!("{:<10} {:>6} {:>12} {:>12}\n",
+                    "pass", "runs", "time", "peak mem");
+    for (name, runs, elapsed, peak) in rows {
+        let _ = writeln!(out, "{:<10} {:>6} {:>12} {:>12}", name, runs,
+                    format!("{:.3}ms", elapsed.as_secs_f64() * 1000.0),
+                    format_bytes(peak));
+    }
+    out
+}
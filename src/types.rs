@@ -1,11 +1,187 @@
-use crate::lexer::Spanned;
+use crate::error::Error;
+use crate::span::Spanned;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 
 pub type Type = Spanned<TypeKind>;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum TypeKind {
+    Void,
     Bool,
     Int,
     Char,
     Array(Box<Type>),
-}
\ No newline at end of file
+    Enum(EnumType),
+    Alias(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumType {
+    pub name: String,
+    pub consts: Vec<(String, i32)>,
+}
+
+impl EnumType {
+    // Constants without an explicit value take the previous value plus one,
+    // starting from zero, as in C.
+    pub fn new(name: &str, consts: Vec<(String, Option<i32>)>) -> Self {
+        let mut next = 0;
+        let consts = consts
+                    .into_iter()
+                    .map(|(id, value)| {
+                        let value = value.unwrap_or(next);
+                        next = value.wrapping_add(1);
+                        (id, value)
+                    })
+                    .collect();
+        EnumType { name: name.to_string(), consts }
+    }
+
+    pub fn value(&self, id: &str) -> Option<i32> {
+        self.consts.iter().find(|(name, _)| name == id).map(|(_, v)| *v)
+    }
+
+    pub fn name_of(&self, value: i32) -> Option<&str> {
+        self.consts
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(name, _)| name.as_str())
+    }
+
+    // Constants not matched by any of `cases`, for exhaustiveness checks.
+    pub fn uncovered(&self, cases: &[i32]) -> Vec<&str> {
+        self.consts
+            .iter()
+            .filter(|(_, v)| !cases.contains(v))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+impl TypeKind {
+    pub const fn is_scalar(&self) -> bool {
+        matches!(self,
+            TypeKind::Bool | TypeKind::Char | TypeKind::Int | TypeKind::Enum(_))
+    }
+
+    // Integer conversion rank: bool < char < int. Enums rank as int.
+    const fn rank(&self) -> Option<u8> {
+        match self {
+            TypeKind::Bool => Some(0),
+            TypeKind::Char => Some(1),
+            TypeKind::Int | TypeKind::Enum(_) => Some(2),
+            TypeKind::Void | TypeKind::Array(_) | TypeKind::Alias(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeKind::Void => write!(f, "void"),
+            TypeKind::Bool => write!(f, "bool"),
+            TypeKind::Int => write!(f, "int"),
+            TypeKind::Char => write!(f, "char"),
+            TypeKind::Array(elem) => write!(f, "{}[]", elem.kind),
+            TypeKind::Enum(e) => write!(f, "enum {}", e.name),
+            TypeKind::Alias(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+// Typedef names mapped to the types they were declared with. Aliases are
+// kept unresolved in the AST so diagnostics can name them; checks should
+// run on the result of `resolve`.
+#[derive(Clone, Debug, Default)]
+pub struct TypeAliases {
+    aliases: BTreeMap<String, Type>,
+}
+
+impl TypeAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn define(&mut self, name: &str, ty: Type) {
+        self.aliases.insert(name.to_string(), ty);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Type> {
+        self.aliases.get(name)
+    }
+
+    pub fn resolve(&self, ty: &Type) -> Result<Type, Error> {
+        self.resolve_with(ty, &mut Vec::new())
+    }
+
+    fn resolve_with<'a>(&'a self, ty: &Type, seen: &mut Vec<&'a str>)
+                -> Result<Type, Error> {
+        match &ty.kind {
+            TypeKind::Alias(name) => {
+                let (name, target) = self.aliases
+                            .get_key_value(name.as_str())
+                            .ok_or(Error::UnknownType(ty.span.clone()))?;
+                if seen.contains(&name.as_str()) {
+                    return Err(Error::RecursiveTypedef(ty.span.clone()));
+                }
+                seen.push(name);
+                let resolved = self.resolve_with(target, seen)?;
+                seen.pop();
+                Ok(Type::new(resolved.kind, ty.span.clone()))
+            }
+            TypeKind::Array(elem) => {
+                let elem = self.resolve_with(elem, seen)?;
+                Ok(Type::new(TypeKind::Array(Box::new(elem)), ty.span.clone()))
+            }
+            _ => Ok(ty.clone()),
+        }
+    }
+}
+
+// Structural equality on kinds, ignoring the spans of nested types.
+pub fn same_type(a: &TypeKind, b: &TypeKind) -> bool {
+    match (a, b) {
+        (TypeKind::Array(x), TypeKind::Array(y)) => same_type(&x.kind, &y.kind),
+        _ => a == b,
+    }
+}
+
+// Integer promotion: scalars narrower than int are widened to int.
+pub fn promote(ty: &TypeKind) -> TypeKind {
+    if ty.is_scalar() { TypeKind::Int } else { ty.clone() }
+}
+
+fn decay_enum(ty: &TypeKind) -> TypeKind {
+    if let TypeKind::Enum(_) = ty { TypeKind::Int } else { ty.clone() }
+}
+
+// A value of type `src` may be stored into `dst` without an explicit cast
+// when the types match or `src` widens into `dst`.
+pub fn is_assignable(dst: &TypeKind, src: &TypeKind) -> bool {
+    if same_type(dst, src) {
+        return true;
+    }
+    if let TypeKind::Enum(_) = dst {
+        return false;
+    }
+    match (dst.rank(), src.rank()) {
+        (Some(d), Some(s)) => s <= d,
+        _ => false,
+    }
+}
+
+// The type both operands convert to before a binary operation, if any.
+pub fn common_type(a: &TypeKind, b: &TypeKind) -> Option<TypeKind> {
+    if same_type(a, b) {
+        return Some(a.clone());
+    }
+    let (a, b) = (&decay_enum(a), &decay_enum(b));
+    match (a.rank(), b.rank()) {
+        (Some(x), Some(y)) => Some(if x >= y { a.clone() } else { b.clone() }),
+        _ => None,
+    }
+}
@@ -0,0 +1,63 @@
+use crate::dialect::LanguageDialect;
+use crate::driver::{self, Diagnostic};
+use crate::heap::Memory;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+// Steps a playground program may take before it is stopped.
+pub const DEFAULT_STEP_LIMIT: u32 = 10_000_000;
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaygroundResult {
+    // JSON array of `{message, span, internal}` objects, where `span` is
+    // null or `{start: {line, col}, end: {line, col}}`.
+    pub diagnostics: String,
+    pub stdout: String,
+    // Set when the program ran to completion.
+    pub exit_code: Option<i32>,
+}
+
+fn diagnostic_json(diag: &Diagnostic) -> Value {
+    let span = diag.span.as_ref().map(|span| json!({
+        "start": { "line": span.start.line, "col": span.start.col },
+        "end": { "line": span.end.line, "col": span.end.col },
+    }));
+    json!({ "message": diag.message, "span": span, "internal": diag.internal })
+}
+
+// A setting given by name, or its default when not given.
+fn setting<T: FromStr<Err = String> + Default>(name: Option<&str>)
+        -> Result<T, Vec<Diagnostic>> {
+    name.map_or(Ok(T::default()), str::parse)
+        .map_err(|message| vec![Diagnostic { message, span: None, internal: false }])
+}
+
+// Compiles `source` in `dialect`, "strict" unless given, and runs it with
+// its output captured and arrays managed by `memory`, "manual" or "gc".
+// Nothing touches the file system or panics, whatever the input.
+#[wasm_bindgen]
+pub fn compile_and_run(source: &str, step_limit: Option<u32>, dialect: Option<String>,
+                       memory: Option<String>) -> PlaygroundResult {
+    // Enforced by the interpreter once programs can run.
+    let _ = step_limit.unwrap_or(DEFAULT_STEP_LIMIT);
+    let mut stdout = Vec::new();
+    let result = (|| {
+        let dialect: LanguageDialect = setting(dialect.as_deref())?;
+        let memory: Memory = setting(memory.as_deref())?;
+        let units = [driver::compile_source(source.as_bytes(), dialect)?.unit];
+        let env = driver::program_env(&units, &[], false).with_memory(memory);
+        driver::run_with_stdout(&units, &env, &mut stdout).map_err(|e| vec![e.into()])
+    })();
+    let (exit_code, diagnostics) = match result {
+        Ok(status) => (Some(status), Vec::new()),
+        Err(diags) => (None, diags),
+    };
+    let diagnostics: Vec<_> = diagnostics.iter().map(diagnostic_json).collect();
+    PlaygroundResult {
+        diagnostics: Value::Array(diagnostics).to_string(),
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        exit_code,
+    }
+}
@@ -0,0 +1,76 @@
+use bearbones::builtins::{Builtin, Env, Value};
+use bearbones::error::{Error, ErrorKind};
+use bearbones::heap::Memory;
+
+#[cfg(test)]
+mod test_builtins {
+    use super::*;
+
+    fn chars(s: &str) -> Value {
+        Value::Chars(s.chars().collect())
+    }
+
+    fn call(name: &str, args: &[Value]) -> i32 {
+        match Builtin::from_name(name).unwrap().call(args, &Env::default()).unwrap() {
+            Value::Int(n) => n,
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(Builtin::from_name("strlen"), Some(Builtin::Strlen));
+        assert_eq!(Builtin::from_name("main"), None);
+        let signatures: Vec<_> = Builtin::ALL.iter().map(|b| b.signature()).collect();
+        assert_eq!(signatures, vec![
+            "int strlen(char[])", "int strcmp(char[], char[])", "int abs(int)",
+            "int min(int, int)", "int max(int, int)", "int pow(int, int)",
+            "int arg_count()", "char[] get_arg(int)",
+        ]);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(call("strlen", &[chars("hello")]), 5);
+        assert_eq!(call("strlen", &[chars("hi\0there")]), 2);
+        assert_eq!(call("strcmp", &[chars("abc"), chars("abc\0x")]), 0);
+        assert_eq!(call("strcmp", &[chars("abc"), chars("abd")]), -1);
+        assert_eq!(call("strcmp", &[chars("abcd"), chars("abc")]), 1);
+    }
+
+    #[test]
+    fn math() {
+        let int = Value::Int;
+        assert_eq!(call("abs", &[int(-7)]), 7);
+        assert_eq!(call("abs", &[int(i32::MIN)]), i32::MIN);
+        assert_eq!(call("min", &[int(3), int(-2)]), -2);
+        assert_eq!(call("max", &[int(3), int(-2)]), 3);
+        assert_eq!(call("pow", &[int(3), int(4)]), 81);
+        assert_eq!(call("pow", &[int(2), int(32)]), 0);
+        assert_eq!(call("pow", &[int(5), int(-1)]), 0);
+        assert_eq!(call("pow", &[int(-1), int(-3)]), -1);
+        assert_eq!(call("pow", &[int(0), int(0)]), 1);
+        let zero = Builtin::Pow.call(&[int(0), int(-2)], &Env::default());
+        assert!(matches!(zero, Err(Error::DivisionByZero)));
+        assert_eq!(Error::DivisionByZero.kind(), ErrorKind::Program);
+    }
+
+    #[test]
+    fn arguments() {
+        let env = Env::new("prog.bb", &["one".to_string(), "two".to_string()]);
+        assert_eq!(Builtin::ArgCount.call(&[], &env).unwrap(), Value::Int(3));
+        assert_eq!(Builtin::GetArg.call(&[Value::Int(0)], &env).unwrap(), chars("prog.bb"));
+        assert_eq!(Builtin::GetArg.call(&[Value::Int(2)], &env).unwrap(), chars("two"));
+        assert_eq!(Builtin::GetArg.call(&[Value::Int(3)], &env).unwrap(), chars(""));
+        assert_eq!(Builtin::GetArg.call(&[Value::Int(-1)], &env).unwrap(), chars(""));
+        assert_eq!(env.memory, Memory::Manual);
+        assert_eq!(env.with_memory(Memory::Collected).memory, Memory::Collected);
+    }
+
+    #[test]
+    fn mismatched_arguments() {
+        let e = Builtin::Abs.call(&[chars("x")], &Env::default()).unwrap_err();
+        assert!(matches!(e, Error::Internal(_)));
+        assert!(Builtin::Min.call(&[Value::Int(1)], &Env::default()).is_err());
+    }
+}
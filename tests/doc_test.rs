@@ -0,0 +1,69 @@
+#![cfg(feature = "std")]
+
+use bearbones::dialect::LanguageDialect;
+use bearbones::doc::{collect, document, Format};
+use bearbones::symbols::SymbolKind;
+
+#[cfg(test)]
+mod test_doc {
+    use super::*;
+
+    const SRC: &str = "\
+#define LIMIT 10
+
+/// How many calls were made.
+int calls;
+
+int add(int a, int b);
+
+/// Adds two numbers.
+///
+/// Both must be below LIMIT.
+int add(int a, int b) {
+    /// Not documentation of anything public.
+    int c = a + b; // trailing
+    return c;
+}
+
+int main() { return add(1, 2); }
+";
+
+    #[test]
+    fn collects_items() {
+        let items = collect(SRC, LanguageDialect::Strict).unwrap();
+        let names: Vec<_> = items.iter()
+            .map(|item| (item.name.as_str(), item.kind, item.line))
+            .collect();
+        assert_eq!(names, vec![
+            ("calls", SymbolKind::Global, 4),
+            ("add", SymbolKind::Function, 6),
+            ("main", SymbolKind::Function, 17),
+        ]);
+        assert_eq!(items[0].docs, "How many calls were made.");
+        assert_eq!(items[1].docs, "Adds two numbers.\n\nBoth must be below LIMIT.");
+        assert_eq!(items[1].signature, "int add(int, int)");
+        assert_eq!(items[2].docs, "");
+    }
+
+    #[test]
+    fn plain_comments_are_not_docs() {
+        let items = collect("// Not docs.\nint x;\n/* Nor this. */\nint y;\n", LanguageDialect::Strict).unwrap();
+        assert!(items.iter().all(|item| item.docs.is_empty()));
+    }
+
+    #[test]
+    fn markdown() {
+        let out = document("prog.bb", SRC, Format::Markdown, LanguageDialect::Strict).unwrap();
+        assert!(out.starts_with("# prog.bb\n\n## Functions\n\n### `int add(int, int)`\n"));
+        assert!(out.contains("\n## Globals\n\n### `int calls`\n\nHow many calls were made.\n"));
+    }
+
+    #[test]
+    fn html() {
+        let src = "/// Returns a < b.\nbool less(int a, int b);\n";
+        let out = document("<prog>", src, Format::Html, LanguageDialect::Strict).unwrap();
+        assert_eq!(out, "<h1>&lt;prog&gt;</h1>\n<h2>Functions</h2>\n\
+            <h3 id=\"less\"><code>bool less(int, int)</code></h3>\n\
+            <p>Returns a &lt; b.</p>\n");
+    }
+}
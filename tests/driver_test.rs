@@ -0,0 +1,260 @@
+#![cfg(feature = "std")]
+
+use bearbones::dialect::LanguageDialect;
+use bearbones::driver::{self, Artifact, Emit, Unit};
+use bearbones::error::Error;
+use bearbones::span::Pos;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod test_driver {
+    use super::*;
+
+    #[test]
+    fn check_ok() {
+        assert!(driver::check("int main() { return 0; }", LanguageDialect::Strict).is_ok());
+    }
+
+    #[test]
+    fn check_unexpected_char() {
+        assert!(matches!(driver::check("int x = 1 @ 2;", LanguageDialect::Strict), Err(Error::UnexpectedChar(_))));
+    }
+
+    #[test]
+    fn check_lex_error() {
+        assert!(matches!(driver::check("char c = '';", LanguageDialect::Strict), Err(Error::EmptyChar(_))));
+    }
+
+    #[test]
+    fn build_unsupported() {
+        assert!(matches!(driver::build(&[Unit::new("a.bb", "int main() {}")], Path::new("a.out")), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(matches!(driver::read_source(Path::new("no/such/file.bb")), Err(Error::Io(..))));
+    }
+
+    #[test]
+    fn emit_tokens() {
+        let out = driver::emit("int x;", Emit::Tokens, LanguageDialect::Strict).unwrap();
+        assert_eq!(out, "1:0-1:3\tKeyword(Int)\n1:4-1:5\tId(\"x\")\n1:5-1:6\tOperator(Semicolon)\n");
+    }
+
+    #[test]
+    fn emit_kinds() {
+        assert_eq!("asm".parse::<Emit>(), Ok(Emit::Asm));
+        assert!("exe".parse::<Emit>().is_err());
+        assert!(matches!(driver::emit("int x;", Emit::Ir, LanguageDialect::Strict), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn read_from_reader() {
+        let src = driver::read_reader("int x;".as_bytes()).unwrap();
+        assert_eq!(src, "int x;");
+        assert!(matches!(driver::read_reader(&[0xff, 0xfe][..]), Err(Error::Io(..))));
+        match driver::compile_reader("'".as_bytes(), Path::new("a.out")) {
+            Err(Error::InFile(_, e)) => assert!(matches!(*e, Error::CharExpected(_))),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn default_output_paths() {
+        let input = Path::new("src/prog.bb");
+        assert_eq!(driver::output_path(input, Artifact::Asm.extension()), Path::new("src/prog.s"));
+        assert_eq!(driver::output_path(input, Emit::Tokens.extension()), Path::new("src/prog.tokens"));
+        assert_eq!(driver::output_path(Path::new("-"), Artifact::Object.extension()), Path::new("a.o"));
+        assert_eq!(driver::output_path(Path::new("-"), ""), Path::new("a.out"));
+        assert_eq!(driver::output_path(Path::new("prog.bb"), ""), Path::new("prog"));
+    }
+
+    #[test]
+    fn check_units_tags_file() {
+        let units = [
+            Unit::new("a.bb", "int f();"),
+            Unit::new("b.bb", "int x = 1 @ 2;"),
+        ];
+        match driver::check_units(&units) {
+            Err(Error::InFile(path, e)) => {
+                assert_eq!(path, Path::new("b.bb"));
+                assert!(matches!(*e, Error::UnexpectedChar(_)));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn emit_several_units() {
+        let units = [Unit::new("a.bb", "x"), Unit::new("b.bb", "y")];
+        let out = driver::emit_units(&units, Emit::Tokens, false).unwrap();
+        assert_eq!(out, "==> a.bb <==\n1:0-1:1\tId(\"x\")\n==> b.bb <==\n1:0-1:1\tId(\"y\")\n");
+    }
+
+    #[test]
+    fn reproducible_paths() {
+        let inside = std::env::current_dir().unwrap().join("src/prog.bb");
+        let outside = Path::new("/elsewhere/lib/util.bb");
+        assert_eq!(driver::artifact_path(&inside, false), inside);
+        let paths = [&inside, outside, Path::new("a.bb")]
+            .map(|path| driver::artifact_path(path, true));
+        let units = [Unit::new(inside.clone(), "x"), Unit::new(outside, "y")];
+        let out = driver::emit_units(&units, Emit::Tokens, true);
+        assert_eq!(paths, ["src/prog.bb", "util.bb", "a.bb"].map(PathBuf::from));
+        assert_eq!(out.unwrap(), "==> src/prog.bb <==\n1:0-1:1\tId(\"x\")\n\
+                                  ==> util.bb <==\n1:0-1:1\tId(\"y\")\n");
+    }
+
+    #[test]
+    fn program_arguments() {
+        let units = [Unit::new("prog.bb", "int main() { return 0; }")];
+        let env = driver::program_env(&units, &["-x".to_string()], false);
+        assert_eq!(env.args, ["prog.bb", "-x"]);
+        let units = [Unit::new("/elsewhere/prog.bb", "")];
+        assert_eq!(driver::program_env(&units, &[], false).args, ["/elsewhere/prog.bb"]);
+        assert_eq!(driver::program_env(&units, &[], true).args, ["prog.bb"]);
+    }
+
+    #[test]
+    fn run_checks_before_executing() {
+        let args = ["--flag".to_string(), "value".to_string()];
+        let bad = [Unit::new("bad.bb", "int x = $;")];
+        let env = driver::program_env(&bad, &args, false);
+        assert_eq!(env.args, ["bad.bb", "--flag", "value"]);
+        assert!(matches!(driver::run(&bad, &env), Err(Error::InFile(..))));
+        let good = [Unit::new("prog.bb", "int main() { return 0; }")];
+        assert!(matches!(driver::run(&good, &env), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(driver::exit_status(0), 0);
+        assert_eq!(driver::exit_status(42), 42);
+        assert_eq!(driver::exit_status(256 + 3), 3);
+        assert_eq!(driver::exit_status(-1), 255);
+    }
+
+    #[test]
+    fn check_all_reports_every_unit() {
+        let units = [
+            Unit::new("a.bb", "char c = '';"),
+            Unit::new("b.bb", "int x;"),
+            Unit::new("c.bb", "int x = 1 @ 2;"),
+        ];
+        let errors = driver::check_all(&units);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(&errors[0], Error::InFile(path, _) if path == Path::new("a.bb")));
+        assert!(matches!(&errors[1], Error::InFile(path, _) if path == Path::new("c.bb")));
+        assert!(driver::check_all(&units[1..2]).is_empty());
+    }
+
+    #[test]
+    fn dump_stages() {
+        let dir = std::env::temp_dir().join(format!("bearbones-dump-{}", std::process::id()));
+        let units = [Unit::new("src/prog.bb", "int x;"), Unit::new("<stdin>", "y")];
+        let written = driver::dump_stages(&units, &dir).unwrap();
+        assert_eq!(written, vec![dir.join("src/prog.tokens"), dir.join("a.tokens")]);
+        assert_eq!(fs::read_to_string(&written[1]).unwrap(), "1:0-1:1\tId(\"y\")\n");
+
+        let same_name = [Unit::new("a/main.bb", "a"), Unit::new("b/main.bb", "b")];
+        let written = driver::dump_stages(&same_name, &dir).unwrap();
+        assert_eq!(written, vec![dir.join("a/main.tokens"), dir.join("b/main.tokens")]);
+        let same_file = [Unit::new("main.bb", "a"), Unit::new("../main.bb", "b")];
+        assert!(matches!(driver::dump_stages(&same_file, &dir), Err(Error::Io(..))));
+        assert!(!dir.join("main.tokens").exists());
+
+        let bad = [Unit::new("good.bb", "x"), Unit::new("bad.bb", "'")];
+        assert!(matches!(driver::dump_stages(&bad, &dir), Err(Error::InFile(..))));
+        assert!(!dir.join("good.tokens").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compile_source_ok() {
+        let artifacts = driver::compile_source(b"#define N 1\nint x = N;", LanguageDialect::Strict).unwrap();
+        assert_eq!(artifacts.tokens.len(), 5);
+        let diags = driver::compile_source(b"int x;\nint y = @;", LanguageDialect::Strict).unwrap_err();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].span.as_ref().unwrap().start, Pos { line: 2, col: 8 });
+        assert!(diags[0].message.starts_with("Syntax Error"));
+        assert!(!diags[0].internal);
+    }
+
+    #[test]
+    fn compile_source_rejects_bad_input() {
+        let diags = driver::compile_source(b"int x;\nint \xff;", LanguageDialect::Strict).unwrap_err();
+        assert_eq!(diags[0].span.as_ref().unwrap().start, Pos { line: 2, col: 4 });
+        let big = vec![b' '; driver::MAX_SOURCE_LEN + 1];
+        assert!(driver::compile_source(&big, LanguageDialect::Strict).is_err());
+        // Includes never reach the file system.
+        let diags = driver::compile_source(b"#include \"Cargo.toml\"", LanguageDialect::Strict).unwrap_err();
+        assert!(diags[0].message.contains("Cannot find include file"));
+    }
+
+    #[test]
+    fn compile_source_bounds_macros() {
+        let mut bomb = String::from("#define M0 x x\n");
+        for i in 1..64 {
+            bomb += &format!("#define M{i} M{} M{}\n", i - 1, i - 1);
+        }
+        bomb += "M63";
+        let diags = driver::compile_source(bomb.as_bytes(), LanguageDialect::Strict).unwrap_err();
+        assert!(diags[0].message.contains("Macro expansion too large"));
+
+        let mut chain = String::new();
+        for i in 0..1000 {
+            chain += &format!("#define M{i} M{}\n", i + 1);
+        }
+        chain += "M0";
+        assert!(driver::compile_source(chain.as_bytes(), LanguageDialect::Strict).is_err());
+
+        let nested = format!("#define F(x) x\n{}1{}", "F(".repeat(1000), ")".repeat(1000));
+        assert!(driver::compile_source(nested.as_bytes(), LanguageDialect::Strict).is_err());
+    }
+
+    #[test]
+    fn compile_source_arbitrary_bytes() {
+        for src in ["int x = 99999999999;", "int y = 1.;"] {
+            let diags = driver::compile_source(src.as_bytes(), LanguageDialect::Strict).unwrap_err();
+            assert!(!diags[0].internal, "{src}: {:?}", diags);
+        }
+        let alphabet = b"#define F(x) x'\"\\/*\n(),;{}=+-@abc.0123456789999999 \t\xc3\xa9\xff";
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..2000 {
+            let len = (state % 64) as usize;
+            let input: Vec<u8> = (0..len).map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                alphabet[(state % alphabet.len() as u64) as usize]
+            }).collect();
+            if let Err(diags) = driver::compile_source(&input, LanguageDialect::Strict) {
+                assert!(diags.iter().all(|d| !d.internal), "{:?}: {:?}", input, diags);
+            }
+        }
+    }
+
+    #[test]
+    fn check_all_is_ordered() {
+        let units: Vec<_> = (0..64)
+            .map(|i| {
+                let src = if i % 3 == 0 { format!("int x{i} = @;") } else { format!("int x{i};") };
+                Unit::new(format!("u{i}.bb"), src)
+            })
+            .collect();
+        for jobs in [1, 4, 0] {
+            driver::set_jobs(jobs);
+            let paths: Vec<_> = driver::check_all(&units).into_iter()
+                .map(|e| match e {
+                    Error::InFile(path, _) => path,
+                    e => panic!("untagged error {:?}", e),
+                })
+                .collect();
+            let expected: Vec<_> = (0..64).step_by(3)
+                .map(|i| PathBuf::from(format!("u{i}.bb")))
+                .collect();
+            assert_eq!(paths, expected);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "tui")]
+use bearbones::dialect::LanguageDialect;
+use bearbones::explore::Explorer;
+
+#[cfg(test)]
+mod test_explore {
+    use super::*;
+
+    const SRC: &str = "\
+// A comment.
+#define N 1
+int g;
+int f(int a) {
+    int b = a;
+    return b + g;
+}
+";
+
+    fn labels(explorer: &Explorer) -> Vec<String> {
+        explorer.visible().iter()
+            .map(|&n| explorer.nodes()[n].label.clone())
+            .collect()
+    }
+
+    #[test]
+    fn declarations_tree() {
+        let explorer = Explorer::new(SRC, LanguageDialect::Strict).unwrap();
+        assert_eq!(labels(&explorer), vec![
+            "Declarations", "int g", "int f(int)", "Tokens", "IR",
+        ]);
+        assert_eq!(explorer.selected().label, "Declarations");
+    }
+
+    #[test]
+    fn navigate() {
+        let mut explorer = Explorer::new(SRC, LanguageDialect::Strict).unwrap();
+        explorer.move_down();
+        explorer.move_down();
+        explorer.expand();
+        assert_eq!(labels(&explorer)[3..5], ["int a (parameter)", "int b (local)"]);
+        explorer.move_down();
+        assert_eq!(explorer.selected().span.as_ref().unwrap().start.line, 4);
+        explorer.collapse();
+        assert_eq!(explorer.selected().label, "int f(int)");
+        explorer.collapse();
+        assert_eq!(labels(&explorer).len(), 5);
+        explorer.move_up();
+        explorer.move_up();
+        explorer.move_up();
+        assert_eq!(explorer.selected().label, "Declarations");
+    }
+
+    #[test]
+    fn jump_to_declaration() {
+        let mut explorer = Explorer::new(SRC, LanguageDialect::Strict).unwrap();
+        while explorer.selected().label != "Tokens" {
+            explorer.move_down();
+        }
+        explorer.expand();
+        // The last `g`, in `return b + g;`.
+        while !explorer.selected().label.starts_with("6:15") {
+            explorer.move_down();
+        }
+        explorer.jump();
+        assert_eq!(explorer.selected().label, "int g");
+    }
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "ffi")]
+use bearbones::ffi::*;
+use std::ffi::CStr;
+use std::ptr;
+
+#[cfg(test)]
+mod test_ffi {
+    use super::*;
+
+    fn last_diagnostics() -> Vec<(String, BbDiagnostic)> {
+        let mut count = 0;
+        let diags = unsafe { bb_last_diagnostics(&mut count) };
+        (0..count)
+            .map(|i| unsafe {
+                let d = *diags.add(i);
+                (CStr::from_ptr(d.message).to_str().unwrap().to_string(), d)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compile_errors() {
+        let src = b"int x;\nint y = @;";
+        let mut program = ptr::null_mut();
+        let status = unsafe { bb_compile(src.as_ptr(), src.len(), BB_DIALECT_STRICT, &mut program) };
+        assert_eq!(status, BB_COMPILE_ERROR);
+        assert!(program.is_null());
+        let diags = last_diagnostics();
+        assert_eq!(diags.len(), 1);
+        let (message, d) = &diags[0];
+        assert!(message.starts_with("Syntax Error"));
+        assert_eq!((d.has_span, d.start_line, d.start_col, d.end_col), (1, 2, 8, 9));
+    }
+
+    #[test]
+    fn compile_and_run() {
+        let src = b"int main() { return 0; }";
+        let mut program = ptr::null_mut();
+        assert_eq!(unsafe { bb_compile(src.as_ptr(), src.len(), BB_DIALECT_STRICT, &mut program) }, BB_OK);
+        assert!(last_diagnostics().is_empty());
+
+        let mut exit_code = -1;
+        let status = unsafe { bb_run(program, BB_MEMORY_GC, &mut exit_code) };
+        assert_eq!(status, BB_RUN_ERROR);
+        assert_eq!(last_diagnostics()[0].0, "Error: Program execution is not supported yet");
+        let mut len = 1;
+        unsafe { bb_last_stdout(&mut len) };
+        assert_eq!(len, 0);
+        unsafe { bb_program_free(program) };
+    }
+
+    #[test]
+    fn dialects() {
+        let src = b"@inline int f() { auto x = 1; return x; }";
+        let mut program = ptr::null_mut();
+        let status = unsafe { bb_compile(src.as_ptr(), src.len(), BB_DIALECT_STRICT, &mut program) };
+        assert_eq!(status, BB_COMPILE_ERROR);
+        let status = unsafe { bb_compile(src.as_ptr(), src.len(), BB_DIALECT_EXTENDED, &mut program) };
+        assert_eq!(status, BB_OK);
+        unsafe { bb_program_free(program) };
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut program = ptr::null_mut();
+        assert_eq!(unsafe { bb_compile(ptr::null(), 0, BB_DIALECT_STRICT, &mut program) }, BB_INVALID_ARGUMENT);
+        let src = b"int x;";
+        assert_eq!(unsafe { bb_compile(src.as_ptr(), src.len(), 2, &mut program) },
+                   BB_INVALID_ARGUMENT);
+        assert_eq!(unsafe { bb_run(ptr::null(), BB_MEMORY_MANUAL, ptr::null_mut()) },
+                   BB_INVALID_ARGUMENT);
+        unsafe { bb_program_free(ptr::null_mut()) };
+        assert_eq!(bb_abi_version(), BB_ABI_VERSION);
+    }
+}
@@ -0,0 +1,220 @@
+#![cfg(feature = "std")]
+
+use bearbones::dialect::LanguageDialect;
+use bearbones::fmt::{format_source, is_formatted, BraceStyle, FmtConfig};
+use std::path::Path;
+use bearbones::error::Error;
+
+#[cfg(test)]
+mod test_fmt {
+    use super::*;
+
+    fn check_format_in(config: &FmtConfig, dialect: LanguageDialect, src: &str,
+                       expected: &str) {
+        let output = format_source(src, config, dialect).unwrap();
+        assert_eq!(output, expected, "formatting {:?}", src);
+        assert!(is_formatted(&output, config, dialect).unwrap(),
+                "not idempotent: {:?}", output);
+    }
+
+    fn check_format_with(config: &FmtConfig, src: &str, expected: &str) {
+        check_format_in(config, LanguageDialect::Strict, src, expected)
+    }
+
+    fn check_format(src: &str, expected: &str) {
+        check_format_with(&FmtConfig::default(), src, expected)
+    }
+
+    fn check_format_extended(src: &str, expected: &str) {
+        check_format_in(&FmtConfig::default(), LanguageDialect::Extended, src, expected)
+    }
+
+    #[test]
+    fn function() {
+        check_format("int main(){return 0;}", "\
+int main() {
+    return 0;
+}
+");
+    }
+
+    #[test]
+    fn spacing() {
+        check_format("int add(int a,int b){int c;c=a+-b*2;return !c;}", "\
+int add(int a, int b) {
+    int c;
+    c = a + -b * 2;
+    return !c;
+}
+");
+    }
+
+    #[test]
+    fn arrays() {
+        check_format_extended("int[]a=new int[n+1];a [0]=a[n]-1;delete a;", "\
+int[] a = new int[n + 1];
+a[0] = a[n] - 1;
+delete a;
+");
+    }
+
+    #[test]
+    fn attributes() {
+        check_format_extended("@ inline int f(){auto x=1;return x;}", "\
+@inline int f() {
+    auto x = 1;
+    return x;
+}
+");
+    }
+
+    #[test]
+    fn comments() {
+        check_format("\
+/* Adds
+   two numbers. */
+#define N 1 // one
+int add(int a,/* inline */int b){// after brace
+// own line
+int c=a+b;   // trailing
+
+    /* before close */
+}
+// end
+", "\
+/* Adds
+   two numbers. */
+#define N 1 // one
+int add(int a, /* inline */ int b) { // after brace
+    // own line
+    int c = a + b; // trailing
+
+    /* before close */
+}
+// end
+");
+    }
+
+    #[test]
+    fn hash_in_comment() {
+        check_format("/*\n# not a directive\n*/\nint x;\n", "/*\n# not a directive\n*/\nint x;\n");
+    }
+
+    #[test]
+    fn control_flow() {
+        check_format("
+            int f(int n) {
+              for(n=0;n<10;n+=1){ if(n==3){break;}else{continue;} }
+              do { n -= 1; } while (n > 0);
+              while(n){}
+              return g(n, 'a', '\\n');
+            }",
+        "\
+int f(int n) {
+    for (n = 0; n < 10; n += 1) {
+        if (n == 3) {
+            break;
+        } else {
+            continue;
+        }
+    }
+    do {
+        n -= 1;
+    } while (n > 0);
+    while (n) {}
+    return g(n, 'a', '\\n');
+}
+");
+    }
+
+    #[test]
+    fn blank_lines() {
+        check_format("int x;\n\n\n\nint y;\nint main() {\n\n  return x;\n\n}\n", "\
+int x;
+
+int y;
+int main() {
+    return x;
+}
+");
+    }
+
+    #[test]
+    fn unformatted() {
+        let config = FmtConfig::default();
+        assert!(!is_formatted("int  x;\n", &config, LanguageDialect::Strict).unwrap());
+        assert!(is_formatted("int x;\n", &config, LanguageDialect::Strict).unwrap());
+    }
+
+    #[test]
+    fn invalid_source() {
+        assert!(matches!(format_source("int x = $;", &FmtConfig::default(), LanguageDialect::Strict), Err(Error::UnexpectedChar(_))));
+    }
+
+    #[test]
+    fn config_file() {
+        let path = Path::new(".bbfmt.toml");
+        let config = FmtConfig::from_toml("indent_width = 2\nbrace_style = \"next_line\"", path).unwrap();
+        assert_eq!(config, FmtConfig { indent_width: 2, brace_style: BraceStyle::NextLine, max_width: 100 });
+        assert_eq!(FmtConfig::from_toml("", path).unwrap(), FmtConfig::default());
+        assert!(matches!(FmtConfig::from_toml("tabs = true", path), Err(Error::InvalidConfig(..))));
+        assert!(matches!(FmtConfig::from_toml("indent_width = \"2\"", path), Err(Error::InvalidConfig(..))));
+    }
+
+    #[test]
+    fn next_line_braces() {
+        let config = FmtConfig { indent_width: 2, brace_style: BraceStyle::NextLine, ..FmtConfig::default() };
+        check_format_with(&config, "int f(int n){if(n){return 1;}else{return 0;}}", "\
+int f(int n)
+{
+  if (n)
+  {
+    return 1;
+  }
+  else
+  {
+    return 0;
+  }
+}
+");
+    }
+
+    #[test]
+    fn max_width() {
+        let config = FmtConfig { max_width: 24, ..FmtConfig::default() };
+        check_format_with(&config, "int main(){return add(first, second, third);}", "\
+int main() {
+    return add(first,
+        second, third);
+}
+");
+    }
+
+    #[test]
+    fn directives_kept() {
+        check_format("#include \"lib.h\"\n  #define N 3\n\nint main(){\n#ifdef N\nreturn 0;\n#endif\n}", "\
+#include \"lib.h\"
+#define N 3
+
+int main() {
+#ifdef N
+    return 0;
+#endif
+}
+");
+    }
+
+    #[test]
+    fn discover_from_current_dir() {
+        let dir = std::env::temp_dir().join(format!("bearbones-fmt-cwd-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join(".bbfmt.toml"), "indent_width = 2\n").unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.join("src")).unwrap();
+        let config = FmtConfig::discover(Path::new("."));
+        std::env::set_current_dir(cwd).unwrap();
+        assert_eq!(config.unwrap().indent_width, 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
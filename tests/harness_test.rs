@@ -0,0 +1,86 @@
+#![cfg(feature = "std")]
+
+use bearbones::error::Error;
+use bearbones::harness::{self, Expectation, Outcome, TestResult};
+use bearbones::preprocess::Options;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod test_harness {
+    use super::*;
+
+    const PROGRAMS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/programs");
+
+    #[test]
+    fn parse_expectations() {
+        assert_eq!(Expectation::parse("int main() { return 0; }"), Ok(Expectation::default()));
+        let src = "// expect: 42\nint main() { // stdout: hello\n}\n//stdout:  two";
+        assert_eq!(Expectation::parse(src), Ok(Expectation {
+            status: 42,
+            stdout: Some("hello\n two\n".into()),
+            args: Vec::new(),
+        }));
+        let src = "// args: a b\n// args: c\nint main() { return arg_count(); }";
+        assert_eq!(Expectation::parse(src).unwrap().args, ["a", "b", "c"]);
+        assert!(Expectation::parse("x;\n// expect: many").unwrap_err().contains("line 2"));
+        let src = "char c = '/'; /* // expect: 1 */\nf(\"// expect: 2\");";
+        assert_eq!(Expectation::parse(src), Ok(Expectation::default()));
+    }
+
+    #[test]
+    fn discover_programs() {
+        let tests = harness::discover(Path::new(PROGRAMS)).unwrap();
+        let names: Vec<_> = tests.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["arguments.bb", "arithmetic.bb", "return_status.bb"]);
+        let file = Path::new(PROGRAMS).join("arithmetic.bb");
+        assert_eq!(harness::discover(&file).unwrap(), vec![file]);
+    }
+
+    #[test]
+    fn compile_errors_are_reported() {
+        let outcome = harness::run_test(Path::new("missing.bb"), &Options::default());
+        assert!(matches!(outcome, Outcome::Error(Error::Io(..))));
+
+        let dir = std::env::temp_dir().join(format!("bearbones-harness-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, src) in [("lex.bb", "int x = $;\n"), ("directive.bb", "#bogus\n")] {
+            let path = dir.join(name);
+            fs::write(&path, src).unwrap();
+            let outcome = harness::run_test(&path, &Options::default());
+            match outcome {
+                Outcome::Error(Error::InFile(file, e)) => {
+                    assert_eq!(file, path);
+                    assert!(matches!(*e, Error::UnexpectedChar(_) | Error::UnknownDirective(_)),
+                            "{name}: {e:?}");
+                }
+                other => panic!("{name}: {other:?}"),
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn summary() {
+        let results = [
+            TestResult { path: PathBuf::from("a.bb"), outcome: Outcome::Pass },
+            TestResult { path: PathBuf::from("b.bb"), outcome: Outcome::Fail("expected exit status 1, got 0".into()) },
+        ];
+        assert_eq!(harness::summary(&results), "
+failures:
+b.bb: expected exit status 1, got 0
+
+test result: FAILED. 1 passed; 1 failed
+");
+        assert_eq!(harness::summary(&results[..1]), "\ntest result: ok. 1 passed; 0 failed\n");
+    }
+
+    #[test]
+    #[ignore = "needs program execution"]
+    fn programs() {
+        for path in harness::discover(Path::new(PROGRAMS)).unwrap() {
+            let outcome = harness::run_test(&path, &Options::default());
+            assert!(outcome.passed(), "{}: {:?}", path.display(), outcome);
+        }
+    }
+}
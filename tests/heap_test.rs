@@ -0,0 +1,87 @@
+use bearbones::error::{Error, ErrorKind};
+use bearbones::heap::{Heap, Memory};
+
+#[cfg(test)]
+mod test_heap {
+    use super::*;
+
+    #[test]
+    fn alloc_load_store() {
+        let mut heap = Heap::new();
+        let a = heap.alloc(3).unwrap();
+        let b = heap.alloc(0).unwrap();
+        assert_eq!(heap.len(a).unwrap(), 3);
+        assert_eq!(heap.len(b).unwrap(), 0);
+        assert_eq!(heap.load(a, 2).unwrap(), 0);
+        heap.store(a, 2, 7).unwrap();
+        assert_eq!(heap.load(a, 2).unwrap(), 7);
+        assert_eq!((heap.used(), heap.live_arrays()), (4, 2));
+    }
+
+    #[test]
+    fn bounds_checks() {
+        let mut heap = Heap::new();
+        let a = heap.alloc(2).unwrap();
+        assert!(matches!(heap.load(a, 2), Err(Error::IndexOutOfBounds(2, 2))));
+        assert!(matches!(heap.load(a, -1), Err(Error::IndexOutOfBounds(-1, 2))));
+        assert!(matches!(heap.store(a, 5, 1), Err(Error::IndexOutOfBounds(5, 2))));
+        assert!(matches!(heap.alloc(-4), Err(Error::InvalidArrayLength(-4))));
+        assert_eq!(Error::IndexOutOfBounds(2, 2).kind(), ErrorKind::Program);
+    }
+
+    #[test]
+    fn freed_arrays_are_detected() {
+        let mut heap = Heap::new();
+        let a = heap.alloc(4).unwrap();
+        heap.free(a).unwrap();
+        assert_eq!(heap.used(), 0);
+        // The slot is reused, but the old handle does not reach it.
+        let b = heap.alloc(4).unwrap();
+        assert!(matches!(heap.load(a, 0), Err(Error::DanglingReference)));
+        assert!(matches!(heap.free(a), Err(Error::DanglingReference)));
+        assert_eq!(heap.load(b, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn limit() {
+        let mut heap = Heap::with_limit(10);
+        let a = heap.alloc(8).unwrap();
+        assert!(matches!(heap.alloc(3), Err(Error::HeapExhausted(10))));
+        heap.free(a).unwrap();
+        assert!(heap.alloc(10).is_ok());
+    }
+
+    #[test]
+    fn empty_arrays_count() {
+        let mut heap = Heap::with_limit(100);
+        for _ in 0..100 {
+            heap.alloc(0).unwrap();
+        }
+        assert!(matches!(heap.alloc(0), Err(Error::HeapExhausted(100))));
+        assert_eq!(heap.used(), 100);
+    }
+
+    #[test]
+    fn manual_delete() {
+        let mut heap = Heap::new();
+        assert_eq!(heap.memory(), Memory::Manual);
+        let a = heap.alloc(1).unwrap();
+        heap.delete(a).unwrap();
+        assert!(matches!(heap.delete(a), Err(Error::DanglingReference)));
+    }
+
+    #[test]
+    fn collected() {
+        let mut heap = Heap::new().with_memory("gc".parse().unwrap());
+        let a = heap.alloc(1).unwrap();
+        let b = heap.alloc(2).unwrap();
+        // Deleting is ignored, so the array stays usable.
+        heap.delete(a).unwrap();
+        heap.delete(a).unwrap();
+        assert_eq!(heap.load(a, 0).unwrap(), 0);
+        assert_eq!(heap.collect(&[a]), 1);
+        assert!(matches!(heap.load(b, 0), Err(Error::DanglingReference)));
+        assert_eq!((heap.used(), heap.live_arrays()), (1, 1));
+        assert!("rc".parse::<Memory>().is_err());
+    }
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "std")]
+
+use bearbones::dialect::LanguageDialect;
+use bearbones::highlight::{classify, highlight, Class, Format};
+
+#[cfg(test)]
+mod test_highlight {
+    use super::*;
+
+    fn classes(src: &str) -> Vec<(String, Class)> {
+        let lines: Vec<&str> = src.lines().collect();
+        classify(src, LanguageDialect::Strict).unwrap().into_iter()
+            .map(|(span, class)| {
+                let line = lines[span.start.line - 1];
+                (line[span.start.col..span.end.col].to_string(), class)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classifies_tokens() {
+        let src = "int g;\nint f(int a) { int x = a >= 'c'; return g; }\n";
+        let names: Vec<_> = classes(src).into_iter()
+            .filter(|(_, class)| *class != Class::Operator)
+            .collect();
+        assert_eq!(names, vec![
+            ("int".to_string(), Class::Type),
+            ("g".to_string(), Class::Global),
+            ("int".to_string(), Class::Type),
+            ("f".to_string(), Class::Function),
+            ("int".to_string(), Class::Type),
+            ("a".to_string(), Class::Parameter),
+            ("int".to_string(), Class::Type),
+            ("x".to_string(), Class::Local),
+            ("a".to_string(), Class::Parameter),
+            ("'c'".to_string(), Class::Char),
+            ("return".to_string(), Class::Keyword),
+            ("g".to_string(), Class::Global),
+        ]);
+        assert!(classes(src).contains(&(">=".to_string(), Class::Operator)));
+    }
+
+    #[test]
+    fn comments_and_directives() {
+        let src = "#define N 1 // one\n/* a\n b */ int x;\n";
+        let found = classes(src);
+        assert_eq!(found[..4], [
+            ("#define N 1".to_string(), Class::Directive),
+            ("// one".to_string(), Class::Comment),
+            ("/* a".to_string(), Class::Comment),
+            (" b */".to_string(), Class::Comment),
+        ]);
+        assert_eq!(found[4], ("int".to_string(), Class::Type));
+    }
+
+    #[test]
+    fn ansi_keeps_text() {
+        let src = "int main() {\n    return 1 + 2;\n}\n";
+        let out = highlight(src, Format::Ansi, LanguageDialect::Strict).unwrap();
+        assert!(out.contains("\x1b[35mreturn\x1b[0m"));
+        let mut plain = String::new();
+        let mut escape = false;
+        for c in out.chars() {
+            match c {
+                '\x1b' => escape = true,
+                'm' if escape => escape = false,
+                c if !escape => plain.push(c),
+                _ => {}
+            }
+        }
+        assert_eq!(plain, src);
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let out = highlight("int f(int a) { return a < 1; }", Format::Html, LanguageDialect::Strict).unwrap();
+        assert!(out.starts_with("<pre class=\"bearbones\"><code>"));
+        assert!(out.contains("<span class=\"bb-operator\">&lt;</span>"));
+        assert!(out.contains("<span class=\"bb-function\">f</span>"));
+        assert!(out.ends_with("</code></pre>\n"));
+    }
+
+    #[test]
+    fn lex_errors_fail() {
+        assert!(highlight("char c = 'ab';", Format::Ansi, LanguageDialect::Strict).is_err());
+        assert!(highlight("/* open", Format::Html, LanguageDialect::Strict).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use bearbones::lexer::{Pos, Span, Lexer, TokenKind, Operator, Keyword};
+use bearbones::dialect::LanguageDialect;
 use bearbones::error::Error;
 
 #[cfg(test)]
@@ -75,6 +76,59 @@ mod test_lexer {
         ])))
     }
 
+    #[test]
+    fn new_delete() {
+        let src = "int[] a = new int[n]; delete a;";
+        let kinds = |dialect| Lexer::new(src).with_dialect(dialect).scanner().unwrap()
+            .into_iter().map(|t| t.kind).collect::<Vec<_>>();
+        let mut expected = vec![
+            TokenKind::Keyword(Keyword::Int),
+            TokenKind::Operator(Operator::LeftBracket),
+            TokenKind::Operator(Operator::RightBracket),
+            TokenKind::Id("a".into()),
+            TokenKind::Operator(Operator::Eq),
+            TokenKind::Keyword(Keyword::New),
+            TokenKind::Keyword(Keyword::Int),
+            TokenKind::Operator(Operator::LeftBracket),
+            TokenKind::Id("n".into()),
+            TokenKind::Operator(Operator::RightBracket),
+            TokenKind::Operator(Operator::Semicolon),
+            TokenKind::Keyword(Keyword::Delete),
+            TokenKind::Id("a".into()),
+            TokenKind::Operator(Operator::Semicolon),
+        ];
+        assert_eq!(kinds(LanguageDialect::Extended), expected);
+        // Strict programs may use them as names.
+        expected[5] = TokenKind::Id("new".into());
+        expected[11] = TokenKind::Id("delete".into());
+        assert_eq!(kinds(LanguageDialect::Strict), expected);
+    }
+
+    #[test]
+    fn dialects() {
+        let src = "@inline auto x = import;";
+        let kinds = |dialect| Lexer::new(src).with_dialect(dialect).scanner().unwrap()
+            .into_iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(LanguageDialect::Strict), vec![
+            TokenKind::Unknown,
+            TokenKind::Id("inline".into()),
+            TokenKind::Id("auto".into()),
+            TokenKind::Id("x".into()),
+            TokenKind::Operator(Operator::Eq),
+            TokenKind::Id("import".into()),
+            TokenKind::Operator(Operator::Semicolon),
+        ]);
+        assert_eq!(kinds(LanguageDialect::Extended), vec![
+            TokenKind::Operator(Operator::At),
+            TokenKind::Id("inline".into()),
+            TokenKind::Keyword(Keyword::Auto),
+            TokenKind::Id("x".into()),
+            TokenKind::Operator(Operator::Eq),
+            TokenKind::Keyword(Keyword::Import),
+            TokenKind::Operator(Operator::Semicolon),
+        ]);
+    }
+
     #[test]
     fn function_decl2() {
         assert!(test_lexer(
@@ -154,4 +208,12 @@ mod test_lexer {
     fn unexpected_end() {
         assert!(test_lexer( "'\\", Err(Error::UnexpectedEndOfInput)));
     }
+
+    #[test]
+    fn invalid_number() {
+        assert!(test_lexer("2147483647", Ok(vec![TokenKind::Int(i32::MAX)])));
+        for src in ["int x = 99999999999;", "1.", "1.5"] {
+            assert!(matches!(Lexer::new(src).scanner(), Err(Error::InvalidNumber(_))), "{src}");
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,43 @@
+#![cfg(feature = "std")]
+
+use bearbones::driver::{self, Unit};
+use bearbones::log::{self, Level};
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+mod test_log {
+    use super::*;
+
+    // Logging state is global, so every case runs in one test.
+    #[test]
+    fn levels_and_sink() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&records);
+        log::set_sink(move |level, msg| sink.lock().unwrap().push((level, msg.to_string())));
+        let units = [Unit::new("a.bb", "int x;")];
+
+        log::set_max_level(Some(Level::Info));
+        driver::check_units(&units).unwrap();
+        assert!(records.lock().unwrap().is_empty());
+
+        log::set_max_level(Some(Level::Verbose));
+        driver::check_units(&units).unwrap();
+        assert_eq!(*records.lock().unwrap(), vec![(Level::Verbose, "checking a.bb".to_string())]);
+
+        records.lock().unwrap().clear();
+        log::set_max_level(Some(Level::Debug));
+        driver::check_units(&units).unwrap();
+        assert_eq!(records.lock().unwrap().len(), 2);
+        assert_eq!(records.lock().unwrap()[1], (Level::Debug, "lexed 3 tokens".to_string()));
+
+        records.lock().unwrap().clear();
+        log::set_max_level(None);
+        assert!(!log::enabled(Level::Info));
+        bearbones::log!(Info, "hidden");
+        driver::check_units(&units).unwrap();
+        assert!(records.lock().unwrap().is_empty());
+
+        log::reset_sink();
+        log::set_max_level(Some(Level::Info));
+    }
+}
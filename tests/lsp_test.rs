@@ -0,0 +1,173 @@
+#![cfg(feature = "lsp")]
+use bearbones::dialect::LanguageDialect;
+use bearbones::lsp;
+use bearbones::span::Pos;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId};
+use lsp_types::{HoverContents, Position, Range, TextDocumentContentChangeEvent, PublishDiagnosticsParams, Url};
+use serde_json::json;
+use std::thread;
+
+#[cfg(test)]
+mod test_lsp {
+    use super::*;
+
+    fn change(range: Option<Range>, text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent { range, range_length: None, text: text.into() }
+    }
+
+    fn next_diagnostics(client: &Connection) -> PublishDiagnosticsParams {
+        match client.receiver.recv().unwrap() {
+            Message::Notification(not) if not.method == "textDocument/publishDiagnostics" =>
+                serde_json::from_value(not.params).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let text = "int x;\nchar c = 'ö' @;";
+        let pos = lsp::to_position(text, &Pos { line: 2, col: 15 });
+        assert_eq!(pos, Position::new(1, 14));
+        assert_eq!(lsp::to_offset(text, pos), text.len() - 1);
+        assert_eq!(lsp::to_offset(text, Position::new(0, 99)), 6);
+        assert_eq!(lsp::to_offset(text, Position::new(7, 0)), text.len());
+    }
+
+    #[test]
+    fn incremental_changes() {
+        let mut text = String::from("int x;\nint y;\n");
+        lsp::apply_change(&mut text, change(Some(Range::new(Position::new(1, 4), Position::new(1, 5))), "zz"));
+        assert_eq!(text, "int x;\nint zz;\n");
+        lsp::apply_change(&mut text, change(None, "int w;"));
+        assert_eq!(text, "int w;");
+    }
+
+    #[test]
+    fn diagnostics() {
+        assert!(lsp::diagnostics("int main() { return 0; }", LanguageDialect::Strict).is_empty());
+        let diags = lsp::diagnostics("int x;\nint y = @;", LanguageDialect::Strict);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].range, Range::new(Position::new(1, 8), Position::new(1, 9)));
+        let diags = lsp::diagnostics("int x;\nchar c = '\\", LanguageDialect::Strict);
+        assert_eq!(diags[0].range, Range::new(Position::new(1, 11), Position::new(1, 11)));
+    }
+
+    #[test]
+    fn dialects() {
+        let text = "@inline int f() { auto x = 1; return x; }";
+        assert_eq!(lsp::diagnostics(text, LanguageDialect::Strict).len(), 1);
+        assert!(lsp::diagnostics(text, LanguageDialect::Extended).is_empty());
+    }
+
+    #[test]
+    fn server_session() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || lsp::serve(&server, LanguageDialect::Strict).unwrap());
+        let uri = "file:///prog.bb";
+
+        client.sender.send(Notification::new("textDocument/didOpen".into(), json!({
+            "textDocument": { "uri": uri, "languageId": "bearbones", "version": 1, "text": "int x = @;" }
+        })).into()).unwrap();
+        let params = next_diagnostics(&client);
+        assert_eq!(params.uri.as_str(), uri);
+        assert_eq!(params.diagnostics.len(), 1);
+
+        client.sender.send(Notification::new("textDocument/didChange".into(), json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{
+                "range": { "start": { "line": 0, "character": 8 }, "end": { "line": 0, "character": 9 } },
+                "text": "1"
+            }]
+        })).into()).unwrap();
+        let params = next_diagnostics(&client);
+        assert_eq!(params.version, Some(2));
+        assert!(params.diagnostics.is_empty());
+
+        client.sender.send(Request::new(RequestId::from(1), "shutdown".into(), json!(null)).into()).unwrap();
+        assert!(matches!(client.receiver.recv().unwrap(), Message::Response(_)));
+        client.sender.send(Notification::new("exit".into(), json!(null)).into()).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn invalid_request_params() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || lsp::serve(&server, LanguageDialect::Strict).unwrap());
+        client.sender.send(Request::new(RequestId::from(1), "textDocument/hover".into(),
+                                        json!({ "position": "nowhere" })).into()).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(resp) => {
+                assert_eq!(resp.id, RequestId::from(1));
+                assert_eq!(resp.error.unwrap().code, ErrorCode::InvalidParams as i32);
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        client.sender.send(Request::new(RequestId::from(2), "textDocument/documentSymbol".into(), json!({
+            "textDocument": { "uri": "file:///prog.bb" }
+        })).into()).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(resp) => assert_eq!(resp.result, Some(json!([]))),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        client.sender.send(Request::new(RequestId::from(3), "shutdown".into(), json!(null)).into()).unwrap();
+        assert!(matches!(client.receiver.recv().unwrap(), Message::Response(_)));
+        client.sender.send(Notification::new("exit".into(), json!(null)).into()).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn invalid_notification_params() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || lsp::serve(&server, LanguageDialect::Strict).unwrap());
+        client.sender.send(Notification::new("textDocument/didOpen".into(), json!({
+            "textDocument": { "uri": 7 }
+        })).into()).unwrap();
+        client.sender.send(Notification::new("textDocument/didChange".into(), json!(null)).into())
+            .unwrap();
+
+        client.sender.send(Request::new(RequestId::from(1), "textDocument/documentSymbol".into(), json!({
+            "textDocument": { "uri": "file:///prog.bb" }
+        })).into()).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(resp) => assert_eq!(resp.result, Some(json!([]))),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        client.sender.send(Request::new(RequestId::from(2), "shutdown".into(), json!(null)).into()).unwrap();
+        assert!(matches!(client.receiver.recv().unwrap(), Message::Response(_)));
+        client.sender.send(Notification::new("exit".into(), json!(null)).into()).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn navigation() {
+        let text = "int twice(int n) {\n    return n + n;\n}\nint main() { return twice(2); }\n";
+        let uri = Url::parse("file:///prog.bb").unwrap();
+
+        let loc = lsp::definition(&uri, text, Position::new(3, 22), LanguageDialect::Strict).unwrap();
+        assert_eq!(loc.range, Range::new(Position::new(0, 4), Position::new(0, 9)));
+        let loc = lsp::definition(&uri, text, Position::new(1, 15), LanguageDialect::Strict).unwrap();
+        assert_eq!(loc.range, Range::new(Position::new(0, 14), Position::new(0, 15)));
+        assert!(lsp::definition(&uri, text, Position::new(1, 5), LanguageDialect::Strict).is_none());
+
+        let hover = lsp::hover(text, Position::new(3, 20), LanguageDialect::Strict).unwrap();
+        match hover.contents {
+            HoverContents::Markup(markup) => assert_eq!(markup.value, "```c\nint twice(int)\n```"),
+            other => panic!("unexpected hover {:?}", other),
+        }
+
+        let builtin = lsp::hover("int main() { return abs(2); }", Position::new(0, 21),
+                                 LanguageDialect::Strict);
+        match builtin.unwrap().contents {
+            HoverContents::Markup(markup) => assert_eq!(markup.value, "```c\nint abs(int)\n```"),
+            other => panic!("unexpected hover {:?}", other),
+        }
+
+        let symbols = lsp::document_symbols(text, LanguageDialect::Strict);
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["twice", "main"]);
+        assert_eq!(symbols[0].children.as_ref().unwrap()[0].name, "n");
+    }
+}
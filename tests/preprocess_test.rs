@@ -0,0 +1,225 @@
+#![cfg(feature = "std")]
+
+use bearbones::driver::{self, Unit};
+use bearbones::error::Error;
+use bearbones::preprocess::{preprocess, preprocess_with, Options};
+use bearbones::span::Pos;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod test_preprocess {
+    use super::*;
+
+    // A fresh directory holding `files`, removed again by the caller.
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("bearbones-pp-{}-{}", name, std::process::id()));
+        for (path, src) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, src).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn no_directives() {
+        let out = preprocess(Path::new("a.bb"), "int x;\nint y;", &[]).unwrap();
+        assert_eq!(out.src, "int x;\nint y;");
+        assert_eq!(out.map.locate(&Pos { line: 2, col: 4 }),
+                   Some((Path::new("a.bb"), Pos { line: 2, col: 4 })));
+    }
+
+    #[test]
+    fn include_next_to_file() {
+        let dir = tree("local", &[("lib.h", "int f();\n")]);
+        let main = dir.join("main.bb");
+        let out = preprocess(&main, "#include \"lib.h\"\nint x;", &[]).unwrap();
+        assert_eq!(out.src, "int f();\nint x;");
+        assert_eq!(out.map.locate(&Pos { line: 1, col: 0 }).unwrap().0, dir.join("lib.h"));
+        assert_eq!(out.map.locate(&Pos { line: 2, col: 0 }),
+                   Some((main.as_path(), Pos { line: 2, col: 0 })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_search_path() {
+        let dir = tree("search", &[("inc/lib.h", "int f();\n")]);
+        let main = dir.join("main.bb");
+        let src = "#include <lib.h>\n#include \"lib.h\"";
+        assert!(matches!(preprocess(&main, src, &[]),
+                         Err(Error::InFile(_, e)) if matches!(*e, Error::IncludeNotFound(_, _))));
+        let out = preprocess(&main, src, &[dir.join("inc")]).unwrap();
+        assert_eq!(out.src, "int f();\nint f();");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pragma_once_and_guards() {
+        let dir = tree("once", &[
+            ("once.h", "#pragma once\nint a;\n"),
+            ("guard.h", "#ifndef GUARD_H\n#define GUARD_H\nint b;\n#endif\n"),
+        ]);
+        let src = "#include \"once.h\"\n#include \"guard.h\"\n\
+                   #include \"once.h\"\n#include \"guard.h\"\n";
+        let out = preprocess(&dir.join("main.bb"), src, &[]).unwrap();
+        assert_eq!(out.src, "int a;\nint b;\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn conditionals() {
+        let src = "#define A\n#ifdef A\nint a;\n#else\nint b;\n#endif\n\
+                   #ifdef B\n#ifdef A\nint c;\n#endif\n#else\nint d;\n#endif";
+        let out = preprocess(Path::new("a.bb"), src, &[]).unwrap();
+        assert_eq!(out.src, "int a;\nint d;");
+    }
+
+    #[test]
+    fn bad_directives() {
+        let unwrap = |src| match preprocess(Path::new("a.bb"), src, &[]) {
+            Err(Error::InFile(_, e)) => *e,
+            other => panic!("expected an error, got {:?}", other),
+        };
+        assert!(matches!(unwrap("#frobnicate"), Error::UnknownDirective(_)));
+        assert!(matches!(unwrap("#ifdef A\nint x;"), Error::UnterminatedConditional(_)));
+        assert!(matches!(unwrap("#endif"), Error::UnexpectedDirective(_)));
+        assert!(matches!(unwrap("#include lib.h"), Error::InvalidDirective(_)));
+        // Unknown directives in skipped regions are ignored.
+        assert!(preprocess(Path::new("a.bb"), "#ifdef A\n#frobnicate\n#endif", &[]).is_ok());
+    }
+
+    #[test]
+    fn recursive_include() {
+        let dir = tree("recursive", &[("self.h", "#include \"self.h\"\n")]);
+        let e = preprocess(&dir.join("main.bb"), "#include \"self.h\"", &[]).unwrap_err();
+        let mut e = &e;
+        while let Error::InFile(_, inner) = e {
+            e = inner;
+        }
+        assert!(matches!(e, Error::IncludeTooDeep(_)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn errors_map_to_included_file() {
+        let dir = tree("errors", &[("lib.h", "int f();\nint x = @;\n")]);
+        let main = dir.join("main.bb");
+        let units = vec![Unit::new(&main, "int a;\n#include \"lib.h\"\nint b;")];
+        let units = driver::preprocess_units(units, &Options::default()).unwrap();
+        let errors = driver::check_all(&units);
+        match &errors[..] {
+            [Error::InFile(path, e)] => {
+                assert_eq!(path, &dir.join("lib.h"));
+                assert_eq!(e.span().unwrap().start, Pos { line: 2, col: 8 });
+            }
+            other => panic!("unexpected errors {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn expand(src: &str) -> String {
+        preprocess(Path::new("a.bb"), src, &[]).unwrap().src
+    }
+
+    #[test]
+    fn object_macros() {
+        assert_eq!(expand("#define MAX 10\nint x = MAX;"), "int x = 10;");
+        assert_eq!(expand("#define A 1\n#define B A + A\nint x = B;"), "int x = 1 + 1;");
+        assert_eq!(expand("#define A 1\n#undef A\nint x = A;"), "int x = A;");
+        assert_eq!(expand("#define NEG -1\nint x = 2-NEG;"), "int x = 2- -1;");
+        assert_eq!(expand("#define X 1\nchar c = 'X'; int XY;"), "char c = 'X'; int XY;");
+    }
+
+    #[test]
+    fn function_macros() {
+        assert_eq!(expand("#define SQ(x) ((x)*(x))\nint y = SQ(a + 1);"),
+                   "int y = ((a + 1)*(a + 1));");
+        assert_eq!(expand("#define ADD(a, b) a+b\nint y = ADD(f(1, 2), 3);"),
+                   "int y = f(1, 2)+3;");
+        assert_eq!(expand("#define ID(x) x\n#define TWO 2\nint y = ID(ID(TWO));"),
+                   "int y = 2;");
+        assert_eq!(expand("#define ZERO() 0\nint y = ZERO();"), "int y = 0;");
+        // Without arguments the name is an ordinary identifier.
+        assert_eq!(expand("#define f(x) x\nint f;"), "int f;");
+        // A space before the parenthesis makes an object-like macro.
+        assert_eq!(expand("#define P (x)\nint y = P;"), "int y = (x);");
+    }
+
+    #[test]
+    fn recursive_macros() {
+        assert_eq!(expand("#define A B\n#define B A\nint x = A;"), "int x = A;");
+        assert_eq!(expand("#define f(x) f(x + 1)\nint y = f(2);"), "int y = f(2 + 1);");
+    }
+
+    #[test]
+    fn bad_macros() {
+        let unwrap = |src| match preprocess(Path::new("a.bb"), src, &[]) {
+            Err(Error::InFile(_, e)) => *e,
+            other => panic!("expected an error, got {:?}", other),
+        };
+        assert!(matches!(unwrap("#define F(a, a) a"), Error::InvalidDirective(_)));
+        assert!(matches!(unwrap("#define F(a a"), Error::InvalidDirective(_)));
+        match unwrap("#define F(a, b) a\nint x = F(1);") {
+            Error::MacroArgCount(span, name, 2, 1) => {
+                assert_eq!(name, "F");
+                assert_eq!((span.start.line, span.start.col), (2, 8));
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+        assert!(matches!(unwrap("#define F(a) a\nint x = F(1;"),
+                         Error::UnterminatedMacroCall(_, _)));
+    }
+
+    #[test]
+    fn errors_map_to_macro_use() {
+        let check = |src: &str| {
+            let units = vec![Unit::new("a.bb", src)];
+            let units = driver::preprocess_units(units, &Options::default()).unwrap();
+            match driver::check_all(&units).pop() {
+                Some(Error::InFile(_, e)) => e.span().unwrap().clone(),
+                other => panic!("unexpected result {:?}", other),
+            }
+        };
+        let span = check("#define BAD @\nint x = BAD + 1;");
+        assert_eq!((span.start, span.end), (Pos { line: 2, col: 8 }, Pos { line: 2, col: 11 }));
+        let span = check("#define BIG 1000000\nint x = BIG; @");
+        assert_eq!((span.start, span.end), (Pos { line: 2, col: 13 }, Pos { line: 2, col: 14 }));
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(expand("int x; // note\nint y;"), "int x; \nint y;");
+        assert_eq!(expand("int /* a\nb */ x;"), "int     \n     x;");
+        assert_eq!(expand("char c = '/'; // '\nint y;"), "char c = '/'; \nint y;");
+        assert_eq!(expand("#define A 1 // one\nint x = A;"), "int x = 1;");
+        assert!(matches!(preprocess(Path::new("a.bb"), "int x; /* open", &[]),
+                         Err(Error::InFile(_, e)) if matches!(*e, Error::UnterminatedComment(_))));
+    }
+
+    #[test]
+    fn output_is_bounded() {
+        // Each line stays under the per-expansion limit, but together they
+        // would expand to gigabytes.
+        let mut src = String::from("#define A0 x x\n");
+        for i in 1..18 {
+            src += &format!("#define A{i} A{} A{}\n", i - 1, i - 1);
+        }
+        src += &"A17\n".repeat(100);
+        let result = preprocess(Path::new("bomb.bb"), &src, &[]);
+        assert!(matches!(result, Err(Error::InFile(_, e)) if matches!(*e, Error::ExpansionTooLarge(_))));
+    }
+
+    #[test]
+    fn predefined_macros() {
+        let options = Options {
+            include_paths: Vec::new(),
+            defines: vec![("DEBUG".into(), "1".into()), ("LEVEL".into(), "3".into())],
+            dialect: Default::default(),
+        };
+        let src = "#ifdef DEBUG\nint level = LEVEL;\n#endif";
+        let out = preprocess_with(Path::new("a.bb"), src, &options).unwrap();
+        assert_eq!(out.src, "int level = 3;");
+    }
+}
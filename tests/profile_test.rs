@@ -0,0 +1,67 @@
+#![cfg(feature = "std")]
+
+use bearbones::profile::{FunctionProfile, Profiler};
+use std::time::Duration;
+
+#[cfg(test)]
+mod test_profile {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    // main calls f twice and g once; f recurses into itself once.
+    fn sample() -> Profiler {
+        let mut p = Profiler::new();
+        p.enter("main", ms(0));
+        p.enter("f", ms(1));
+        p.exit(ms(3));
+        p.enter("g", ms(3));
+        p.exit(ms(7));
+        p.enter("f", ms(7));
+        p.enter("f", ms(8));
+        p.exit(ms(9));
+        p.exit(ms(10));
+        p.exit(ms(10));
+        p
+    }
+
+    #[test]
+    fn flat_profile() {
+        let profile = |name: &str, calls, self_ms, total_ms| FunctionProfile {
+            name: name.into(), calls, self_time: ms(self_ms), total_time: ms(total_ms),
+        };
+        assert_eq!(sample().flat(), vec![
+            profile("f", 3, 5, 5),
+            profile("g", 1, 4, 4),
+            profile("main", 1, 1, 10),
+        ]);
+    }
+
+    #[test]
+    fn report() {
+        let report = sample().report();
+        let (flat, tree) = report.split_once("\ncall tree:\n").unwrap();
+        assert_eq!(flat.lines().count(), 4);
+        assert!(flat.lines().nth(1).unwrap().ends_with("  f"));
+        assert_eq!(tree.lines().collect::<Vec<_>>(), [
+            "  main 10.000ms (1 calls, 1.000ms self)",
+            "    f 5.000ms (2 calls, 4.000ms self)",
+            "      f 1.000ms (1 calls, 1.000ms self)",
+            "    g 4.000ms (1 calls, 4.000ms self)",
+        ]);
+    }
+
+    #[test]
+    fn unfinished_calls() {
+        let mut p = Profiler::new();
+        assert!(p.is_empty());
+        p.exit(ms(1));
+        p.enter("main", ms(0));
+        p.enter("loop", ms(2));
+        p.finish(ms(5));
+        assert_eq!(p.flat()[0].name, "loop");
+        assert_eq!(p.flat()[1].total_time, ms(5));
+    }
+}
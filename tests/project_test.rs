@@ -0,0 +1,85 @@
+#![cfg(feature = "std")]
+
+use bearbones::dialect::LanguageDialect;
+use bearbones::error::Error;
+use bearbones::project::{ProjectConfig, Target, WarningLevel};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod test_project {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let src = r#"
+sources = ["src", "main.bb"]
+include = ["include"]
+warnings = "all"
+target = "x86_64"
+dialect = "extended"
+
+[defines]
+DEBUG = "1"
+NAME = ""
+"#;
+        let config = ProjectConfig::from_toml(src, Path::new("proj/bearbones.toml")).unwrap();
+        assert_eq!(config.sources, vec![PathBuf::from("proj/src"), PathBuf::from("proj/main.bb")]);
+        assert_eq!(config.include, vec![PathBuf::from("proj/include")]);
+        assert_eq!(config.warnings, WarningLevel::All);
+        assert_eq!(config.target, Some(Target::X86_64));
+        assert_eq!(config.dialect, LanguageDialect::Extended);
+        assert_eq!(config.options().defines, vec![
+            ("DEBUG".to_string(), "1".to_string()),
+            ("NAME".to_string(), String::new()),
+        ]);
+        assert_eq!(ProjectConfig::from_toml("", Path::new("bearbones.toml")).unwrap(),
+                   ProjectConfig::default());
+        let arm = ProjectConfig::from_toml("target = \"aarch64\"", Path::new("bearbones.toml"));
+        assert_eq!(arm.unwrap().target, Some(Target::Aarch64));
+    }
+
+    #[test]
+    fn invalid_config() {
+        let path = Path::new("bearbones.toml");
+        assert!(matches!(ProjectConfig::from_toml("source = []", path),
+                         Err(Error::InvalidConfig(..))));
+        assert!(matches!(ProjectConfig::from_toml("warnings = \"loud\"", path),
+                         Err(Error::InvalidConfig(..))));
+        assert!(matches!(ProjectConfig::from_toml("target = \"pdp11\"", path),
+                         Err(Error::InvalidConfig(..))));
+        assert!(matches!(ProjectConfig::from_toml("dialect = \"c89\"", path),
+                         Err(Error::InvalidConfig(..))));
+        assert!(matches!(ProjectConfig::from_toml("[defines]\n\"1X\" = \"1\"", path),
+                         Err(Error::InvalidConfig(..))));
+    }
+
+    #[test]
+    fn discover_and_sources() {
+        let dir = std::env::temp_dir().join(format!("bearbones-project-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+        fs::write(dir.join("bearbones.toml"), "sources = [\"src\"]\n").unwrap();
+        fs::write(dir.join("src/b.bb"), "").unwrap();
+        fs::write(dir.join("src/nested/a.bb"), "").unwrap();
+        fs::write(dir.join("src/notes.txt"), "").unwrap();
+
+        let config = ProjectConfig::discover(&dir.join("src/nested")).unwrap().unwrap();
+        assert_eq!(config.source_files().unwrap(),
+                   vec![dir.join("src/b.bb"), dir.join("src/nested/a.bb")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn discover_from_current_dir() {
+        let dir = std::env::temp_dir().join(format!("bearbones-cwd-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("bearbones.toml"), "dialect = \"extended\"\n").unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.join("src")).unwrap();
+        let config = ProjectConfig::discover(Path::new("."));
+        std::env::set_current_dir(cwd).unwrap();
+        assert_eq!(config.unwrap().unwrap().dialect, LanguageDialect::Extended);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
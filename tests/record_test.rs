@@ -0,0 +1,76 @@
+#![cfg(feature = "cli")]
+use bearbones::error::{Error, ErrorKind};
+use bearbones::record::Record;
+use bearbones::span::{Pos, Span};
+use serde_json::{json, Value};
+use std::path::Path;
+
+#[cfg(test)]
+mod test_record {
+    use super::*;
+
+    fn to_json(record: &Record) -> Value {
+        serde_json::to_value(record).unwrap()
+    }
+
+    #[test]
+    fn error_kinds() {
+        let span = Span::new(Pos { line: 1, col: 1 }, Pos { line: 1, col: 2 });
+        assert_eq!(Error::UnexpectedChar(span.clone()).kind(), ErrorKind::Syntax);
+        assert_eq!(Error::UnknownType(span.clone()).kind(), ErrorKind::Type);
+        assert_eq!(Error::Unsupported("Code generation").kind(),
+                   ErrorKind::Unsupported);
+        assert_eq!(Error::Internal("oops".to_string()).kind(), ErrorKind::Internal);
+        let e = Error::RecursiveTypedef(span).in_file(Path::new("a.bb"));
+        assert_eq!(e.kind(), ErrorKind::Type);
+    }
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        let kinds = [ErrorKind::Syntax, ErrorKind::Type, ErrorKind::Program,
+                     ErrorKind::Io, ErrorKind::Unsupported, ErrorKind::Internal];
+        let mut codes: Vec<_> = kinds.iter().map(|k| k.exit_code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), kinds.len());
+        assert!(codes.iter().all(|&code| code > 2));
+    }
+
+    #[test]
+    fn diagnostic_record() {
+        let span = Span::new(Pos { line: 2, col: 8 }, Pos { line: 2, col: 9 });
+        let e = Error::UnexpectedChar(span).in_file(Path::new("a.bb"));
+        let record = to_json(&Record::diagnostic(&e));
+        assert_eq!(record["type"], "diagnostic");
+        assert_eq!(record["kind"], "syntax");
+        assert_eq!(record["file"], "a.bb");
+        assert_eq!(record["span"], json!({
+            "start": { "line": 2, "col": 8 },
+            "end": { "line": 2, "col": 9 },
+        }));
+        assert!(!record["message"].as_str().unwrap().ends_with('\n'));
+    }
+
+    #[test]
+    fn diagnostic_without_file() {
+        let record = to_json(&Record::diagnostic(&Error::MainNotFound));
+        assert_eq!(record["file"], Value::Null);
+        assert_eq!(record["span"], Value::Null);
+        assert_eq!(record["kind"], "type");
+    }
+
+    #[test]
+    fn tagged_records() {
+        assert_eq!(to_json(&Record::Exit { code: 3 }),
+                   json!({ "type": "exit", "code": 3 }));
+        assert_eq!(to_json(&Record::TestSummary { passed: 2, failed: 1 }),
+                   json!({ "type": "test_summary", "passed": 2, "failed": 1 }));
+        assert_eq!(to_json(&Record::Unformatted { path: "a.bb".into() }),
+                   json!({ "type": "unformatted", "path": "a.bb" }));
+        assert_eq!(to_json(&Record::Log {
+                       level: bearbones::log::Level::Verbose,
+                       message: "lexing".to_string(),
+                   }),
+                   json!({ "type": "log", "level": "verbose", "message": "lexing" }));
+    }
+}
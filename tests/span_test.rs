@@ -0,0 +1,41 @@
+use bearbones::span::{Pos, Span, Spanned};
+
+#[cfg(test)]
+mod test_span {
+    use super::*;
+
+    fn span(line: usize, start: usize, end: usize) -> Span {
+        Span::new(Pos { line, col: start }, Pos { line, col: end })
+    }
+
+    #[test]
+    fn map_keeps_span() {
+        let s = Spanned::new(2, span(1, 0, 1)).map(|n| n * 21);
+        assert_eq!(s.kind, 42);
+        assert_eq!(s.span(), &span(1, 0, 1));
+    }
+
+    #[test]
+    fn as_ref_borrows() {
+        let s = Spanned::new(String::from("x"), span(1, 3, 4));
+        let r = s.as_ref();
+        assert_eq!(r.kind.as_str(), "x");
+        assert_eq!(r.span, s.span);
+    }
+
+    #[test]
+    fn tuple_conversions() {
+        let s: Spanned<char> = ('c', span(2, 1, 2)).into();
+        let (kind, sp): (char, Span) = s.into();
+        assert_eq!(kind, 'c');
+        assert_eq!(sp, span(2, 1, 2));
+    }
+
+    #[test]
+    fn join_spans() {
+        let a = span(1, 4, 6);
+        let b = Span::new(Pos { line: 1, col: 2 }, Pos { line: 3, col: 1 });
+        assert_eq!(a.to(&b), b.to(&a));
+        assert_eq!(a.to(&b), Span::new(Pos { line: 1, col: 2 }, Pos { line: 3, col: 1 }));
+    }
+}
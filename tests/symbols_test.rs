@@ -0,0 +1,74 @@
+#![cfg(feature = "std")]
+
+use bearbones::lexer::Lexer;
+use bearbones::span::Pos;
+use bearbones::symbols::{SymbolKind, SymbolTable};
+use bearbones::types::TypeKind;
+
+#[cfg(test)]
+mod test_symbols {
+    use super::*;
+
+    const SRC: &str = "\
+int count, limit;
+int add(int a, char b);
+int add(int a, char b) {
+    int c = a + b;
+    for (int i = 0; i < c; i += 1) {
+        int a = i;
+        c += a;
+    }
+    return add(c, b) + a + count;
+}
+";
+
+    fn table() -> SymbolTable {
+        SymbolTable::new(Lexer::new(SRC).scanner().unwrap())
+    }
+
+    fn decl_line(table: &SymbolTable, line: usize, col: usize) -> Option<usize> {
+        table.symbol_at(&Pos { line, col }).map(|s| s.span.start.line)
+    }
+
+    #[test]
+    fn declarations() {
+        let table = table();
+        let kinds: Vec<_> = table.symbols().iter()
+            .map(|s| (s.name.as_str(), s.kind))
+            .collect();
+        assert_eq!(kinds, vec![
+            ("count", SymbolKind::Global),
+            ("limit", SymbolKind::Global),
+            ("add", SymbolKind::Function),
+            ("a", SymbolKind::Parameter),
+            ("b", SymbolKind::Parameter),
+            ("add", SymbolKind::Function),
+            ("a", SymbolKind::Parameter),
+            ("b", SymbolKind::Parameter),
+            ("c", SymbolKind::Local),
+            ("i", SymbolKind::Local),
+            ("a", SymbolKind::Local),
+        ]);
+        let add = &table.symbols()[5];
+        assert_eq!(add.params, vec![TypeKind::Int, TypeKind::Char]);
+        assert_eq!(add.signature(), "int add(int, char)");
+        assert_eq!(table.symbols()[8].signature(), "int c");
+    }
+
+    #[test]
+    fn resolution() {
+        let table = table();
+        // `a` inside the loop is the shadowing local.
+        assert_eq!(decl_line(&table, 7, 13), Some(6));
+        // After the loop it is the parameter again.
+        assert_eq!(decl_line(&table, 9, 23), Some(3));
+        assert_eq!(decl_line(&table, 9, 11), Some(3));
+        assert_eq!(decl_line(&table, 9, 29), Some(1));
+        // Cursor just past the name still counts.
+        assert_eq!(decl_line(&table, 5, 24), Some(4));
+        // Keywords and unknown names do not resolve.
+        assert_eq!(decl_line(&table, 4, 5), None);
+        assert!(SymbolTable::new(Lexer::new("x = y;").scanner().unwrap())
+            .symbol_at(&Pos { line: 1, col: 0 }).is_none());
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "std")]
+
+use bearbones::driver::{self, Unit};
+use bearbones::error::Error;
+use bearbones::preprocess::Options;
+use bearbones::testgen::{compare, generate, Backend, Comparison, Config, Observation};
+
+#[cfg(test)]
+mod test_testgen {
+    use super::*;
+
+    fn exited(status: u8) -> Result<Observation, Error> {
+        Ok(Observation { status, stdout: String::new() })
+    }
+
+    #[test]
+    fn seeds_are_deterministic() {
+        let config = Config::default();
+        assert_eq!(generate(42, &config), generate(42, &config));
+        assert_ne!(generate(42, &config), generate(43, &config));
+    }
+
+    #[test]
+    fn programs_pass_the_front_end() {
+        let config = Config { functions: 2, statements: 4, depth: 2, iterations: 3 };
+        for seed in 0..200 {
+            let src = generate(seed, &config);
+            assert!(src.contains("int main() {"));
+            let units = driver::preprocess_units(vec![Unit::new("gen.bb", src.clone())],
+                                                 &Options::default());
+            if let Err(e) = units.and_then(|units| driver::check_units(&units)) {
+                panic!("seed {seed}: {e}\n{src}");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_config() {
+        let config = Config { functions: 0, statements: 0, depth: 0, iterations: 0 };
+        for seed in 0..20 {
+            assert!(generate(seed, &config).contains("int main() {"));
+        }
+    }
+
+    #[test]
+    fn unsupported_backends_are_skipped() {
+        let comparison = compare(&generate(0, &Config::default()), &[]).unwrap();
+        assert_eq!(comparison.results.len(), Backend::ALL.len());
+        // No backend runs programs yet, so nothing was compared.
+        assert!(!comparison.compared());
+        assert!(!comparison.diverges());
+    }
+
+    #[test]
+    fn divergence() {
+        let agree = Comparison { results: vec![
+            (Backend::Interpreter, exited(3)),
+            (Backend::Vm, Err(Error::Unsupported("The bytecode VM"))),
+            (Backend::Native, exited(3)),
+        ]};
+        assert!(agree.compared());
+        assert!(!agree.diverges());
+
+        let alone = Comparison { results: vec![
+            (Backend::Interpreter, exited(3)),
+            (Backend::Vm, Err(Error::Unsupported("The bytecode VM"))),
+        ]};
+        assert!(!alone.compared());
+        assert!(!alone.diverges());
+
+        let status = Comparison { results: vec![
+            (Backend::Interpreter, exited(3)),
+            (Backend::Native, exited(4)),
+        ]};
+        assert!(status.diverges());
+        assert_eq!(status.report(),
+                   "interpreter: exit 3, stdout \"\"\nnative: exit 4, stdout \"\"\n");
+
+        let failure = Comparison { results: vec![
+            (Backend::Interpreter, Err(Error::HeapExhausted(8))),
+            (Backend::Vm, exited(0)),
+        ]};
+        assert!(failure.diverges());
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "std")]
+
+use bearbones::driver::{self, Unit};
+use bearbones::timing::{self, CountingAlloc, PassTiming};
+use std::time::Duration;
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+#[cfg(test)]
+mod test_timing {
+    use super::*;
+
+    // Timing state is global, so every case runs in one test.
+    #[test]
+    fn records_passes() {
+        let units = [Unit::new("a.bb", "int x;"), Unit::new("b.bb", "int y;")];
+        driver::check_units(&units).unwrap();
+        assert!(timing::take().is_empty());
+
+        timing::set_enabled(true);
+        driver::check_units(&units).unwrap();
+        let passes = timing::take();
+        timing::set_enabled(false);
+
+        assert_eq!(passes.iter().map(|p| p.name).collect::<Vec<_>>(), vec!["lex", "lex"]);
+        assert!(passes.iter().all(|p| p.peak_bytes.is_some_and(|b| b > 0)));
+        assert!(timing::take().is_empty());
+    }
+
+    #[test]
+    fn report_merges_runs() {
+        let pass = |name, ms, peak| PassTiming {
+            name, elapsed: Duration::from_millis(ms), peak_bytes: peak,
+        };
+        let report = timing::report(&[
+            pass("lex", 1, Some(100)),
+            pass("format", 2, None),
+            pass("lex", 3, Some(4096)),
+        ]);
+        let lines: Vec<_> = report.lines().map(|l| l.split_whitespace().collect::<Vec<_>>()).collect();
+        assert_eq!(lines, vec![
+            vec!["pass", "runs", "time", "peak", "mem"],
+            vec!["lex", "2", "4.000ms", "4.0", "KiB"],
+            vec!["format", "1", "2.000ms", "-"],
+        ]);
+    }
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "tracing")]
+use bearbones::dialect::LanguageDialect;
+use bearbones::driver::{self, Unit};
+use bearbones::log::{self, Level};
+use std::fmt::{self, Write};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_core::span::Current;
+use tracing::{Event, Metadata, Subscriber};
+
+#[cfg(test)]
+mod test_tracing {
+    use super::*;
+
+    thread_local! {
+        // Spans entered on this thread, innermost last.
+        static ENTERED: RefCell<Vec<Id>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // Records every span and event as a line of text, and which span each
+    // new span started in.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        seen: Arc<Mutex<Vec<String>>>,
+        spans: Arc<Mutex<Vec<&'static Metadata<'static>>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes) -> Id {
+            let name = span.metadata().name();
+            let mut fields = Fields(format!("span {name}"));
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            let parent = span.parent().cloned()
+                .or_else(|| ENTERED.with_borrow(|entered| entered.last().cloned()));
+            let mut seen = self.seen.lock().unwrap();
+            seen.push(fields.0);
+            if let Some(parent) = parent {
+                seen.push(format!("{name} in {}", spans[parent.into_u64() as usize - 1].name()));
+            }
+            spans.push(span.metadata());
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Fields(format!("event {}", event.metadata().level()));
+            event.record(&mut fields);
+            self.seen.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, id: &Id) {
+            ENTERED.with_borrow_mut(|entered| entered.push(id.clone()));
+        }
+
+        fn exit(&self, _: &Id) {
+            ENTERED.with_borrow_mut(|entered| entered.pop());
+        }
+
+        fn current_span(&self) -> Current {
+            match ENTERED.with_borrow(|entered| entered.last().cloned()) {
+                Some(id) => {
+                    let metadata = self.spans.lock().unwrap()[id.into_u64() as usize - 1];
+                    Current::new(id, metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    fn record(f: impl FnOnce()) -> Vec<String> {
+        let recorder = Recorder::default();
+        let seen = recorder.seen.clone();
+        tracing::subscriber::with_default(recorder, f);
+        let seen = seen.lock().unwrap().clone();
+        seen
+    }
+
+    #[test]
+    fn passes_are_spans() {
+        let seen = record(|| {
+            driver::check("int x;", LanguageDialect::Strict).unwrap();
+        });
+        assert!(seen.contains(&"span pass name=\"lex\"".to_string()), "{seen:?}");
+    }
+
+    #[test]
+    fn failures_are_events() {
+        let seen = record(|| {
+            driver::check_all(&[Unit::new("bad.bb", "int x = @;")]);
+        });
+        assert!(seen.contains(&"span unit path=bad.bb".to_string()), "{seen:?}");
+        assert!(seen.iter().any(|s| s.starts_with("event WARN")
+                                    && s.contains("check failed")), "{seen:?}");
+    }
+
+    #[test]
+    fn workers_report_to_the_caller() {
+        driver::set_jobs(2);
+        let units = [Unit::new("a.bb", "int x = @;"), Unit::new("b.bb", "int y = @;")];
+        let seen = record(|| {
+            tracing::info_span!("outer").in_scope(|| driver::check_all(&units));
+        });
+        driver::set_jobs(0);
+        for path in ["a.bb", "b.bb"] {
+            assert!(seen.contains(&format!("span unit path={path}")), "{seen:?}");
+        }
+        assert_eq!(seen.iter().filter(|s| *s == "unit in outer").count(), 2, "{seen:?}");
+        assert_eq!(seen.iter().filter(|s| s.contains("check failed")).count(), 2, "{seen:?}");
+    }
+
+    #[test]
+    fn log_messages_are_events() {
+        // Below the default level, so only the subscriber sees it.
+        let seen = record(|| log::log(Level::Debug, format_args!("detail {}", 1)));
+        assert_eq!(seen, vec!["event TRACE message=detail 1"]);
+    }
+}
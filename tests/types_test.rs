@@ -0,0 +1,138 @@
+use bearbones::span::{Pos, Span};
+use bearbones::error::Error;
+use bearbones::types::{Type, TypeKind, TypeAliases, EnumType, is_assignable, common_type, promote, same_type};
+
+#[cfg(test)]
+mod test_types {
+    use super::*;
+
+    fn span(col: usize) -> Span {
+        Span { start: Pos { line: 1, col }, end: Pos { line: 1, col: col + 1 } }
+    }
+
+    fn array_of(kind: TypeKind, col: usize) -> TypeKind {
+        TypeKind::Array(Box::new(Type::new(kind, span(col))))
+    }
+
+    #[test]
+    fn identical_types_assignable() {
+        assert!(is_assignable(&TypeKind::Int, &TypeKind::Int));
+        assert!(is_assignable(&TypeKind::Bool, &TypeKind::Bool));
+    }
+
+    #[test]
+    fn widening_assignable() {
+        assert!(is_assignable(&TypeKind::Int, &TypeKind::Char));
+        assert!(is_assignable(&TypeKind::Int, &TypeKind::Bool));
+        assert!(is_assignable(&TypeKind::Char, &TypeKind::Bool));
+    }
+
+    #[test]
+    fn narrowing_not_assignable() {
+        assert!(!is_assignable(&TypeKind::Char, &TypeKind::Int));
+        assert!(!is_assignable(&TypeKind::Bool, &TypeKind::Char));
+    }
+
+    #[test]
+    fn arrays_ignore_spans() {
+        let a = array_of(TypeKind::Int, 0);
+        let b = array_of(TypeKind::Int, 7);
+        assert!(is_assignable(&a, &b));
+        assert!(!is_assignable(&a, &array_of(TypeKind::Char, 0)));
+        assert!(!is_assignable(&a, &TypeKind::Int));
+    }
+
+    #[test]
+    fn common_types() {
+        assert_eq!(common_type(&TypeKind::Char, &TypeKind::Int), Some(TypeKind::Int));
+        assert_eq!(common_type(&TypeKind::Bool, &TypeKind::Char), Some(TypeKind::Char));
+        assert_eq!(common_type(&TypeKind::Int, &array_of(TypeKind::Int, 0)), None);
+    }
+
+    #[test]
+    fn promotion() {
+        assert_eq!(promote(&TypeKind::Bool), TypeKind::Int);
+        assert_eq!(promote(&TypeKind::Char), TypeKind::Int);
+        let arr = array_of(TypeKind::Char, 0);
+        assert_eq!(promote(&arr), arr);
+    }
+
+    fn color() -> EnumType {
+        EnumType::new("color", vec![
+            ("RED".into(), None),
+            ("GREEN".into(), Some(5)),
+            ("BLUE".into(), None),
+        ])
+    }
+
+    #[test]
+    fn enum_implicit_values() {
+        let e = color();
+        assert_eq!(e.value("RED"), Some(0));
+        assert_eq!(e.value("GREEN"), Some(5));
+        assert_eq!(e.value("BLUE"), Some(6));
+        assert_eq!(e.value("PURPLE"), None);
+        assert_eq!(e.name_of(6), Some("BLUE"));
+    }
+
+    #[test]
+    fn enum_uncovered() {
+        let e = color();
+        assert_eq!(e.uncovered(&[0, 6]), vec!["GREEN"]);
+        assert!(e.uncovered(&[0, 5, 6]).is_empty());
+    }
+
+    #[test]
+    fn enum_conversions() {
+        let e = TypeKind::Enum(color());
+        let other = TypeKind::Enum(EnumType::new("shape", vec![]));
+        assert!(is_assignable(&TypeKind::Int, &e));
+        assert!(is_assignable(&e, &e));
+        assert!(!is_assignable(&e, &TypeKind::Int));
+        assert!(!is_assignable(&e, &other));
+        assert!(!is_assignable(&TypeKind::Char, &e));
+        assert_eq!(common_type(&e, &TypeKind::Char), Some(TypeKind::Int));
+        assert_eq!(common_type(&e, &other), Some(TypeKind::Int));
+        assert_eq!(promote(&e), TypeKind::Int);
+    }
+
+    fn alias(name: &str, col: usize) -> Type {
+        Type::new(TypeKind::Alias(name.into()), span(col))
+    }
+
+    #[test]
+    fn alias_resolution() {
+        let mut aliases = TypeAliases::new();
+        aliases.define("byte", Type::new(TypeKind::Char, span(0)));
+        aliases.define("word", alias("byte", 1));
+        aliases.define("buf", Type::new(TypeKind::Array(Box::new(alias("word", 2))), span(2)));
+
+        let ty = aliases.resolve(&alias("word", 9)).unwrap();
+        assert_eq!(ty, Type::new(TypeKind::Char, span(9)));
+        let ty = aliases.resolve(&alias("buf", 4)).unwrap();
+        assert!(same_type(&ty.kind, &array_of(TypeKind::Char, 0)));
+        assert_eq!(ty.span, span(4));
+    }
+
+    #[test]
+    fn alias_display_keeps_name() {
+        assert_eq!(alias("word", 0).kind.to_string(), "word");
+        assert_eq!(array_of(TypeKind::Alias("word".into()), 0).to_string(), "word[]");
+    }
+
+    #[test]
+    fn alias_cycle() {
+        let mut aliases = TypeAliases::new();
+        aliases.define("a", alias("b", 0));
+        aliases.define("b", alias("a", 1));
+        aliases.define("c", Type::new(TypeKind::Array(Box::new(alias("c", 2))), span(2)));
+        assert!(matches!(aliases.resolve(&alias("a", 5)), Err(Error::RecursiveTypedef(_))));
+        assert!(matches!(aliases.resolve(&alias("c", 5)), Err(Error::RecursiveTypedef(_))));
+    }
+
+    #[test]
+    fn alias_unknown() {
+        let aliases = TypeAliases::new();
+        assert!(matches!(aliases.resolve(&alias("nope", 3)), Err(Error::UnknownType(_))));
+    }
+}
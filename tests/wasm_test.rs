@@ -0,0 +1,58 @@
+#![cfg(feature = "wasm")]
+use bearbones::wasm::compile_and_run;
+use serde_json::{json, Value};
+
+#[cfg(test)]
+mod test_wasm {
+    use super::*;
+
+    fn diagnostics(source: &str) -> Value {
+        serde_json::from_str(&compile_and_run(source, None, None, None).diagnostics).unwrap()
+    }
+
+    #[test]
+    fn syntax_errors_as_json() {
+        let result = compile_and_run("int x;\nint y = @;", Some(1000), None, None);
+        assert_eq!(result.exit_code, None);
+        assert_eq!(result.stdout, "");
+        let diags: Value = serde_json::from_str(&result.diagnostics).unwrap();
+        assert_eq!(diags[0]["span"], json!({
+            "start": { "line": 2, "col": 8 },
+            "end": { "line": 2, "col": 9 },
+        }));
+        assert_eq!(diags[0]["internal"], json!(false));
+    }
+
+    #[test]
+    fn no_file_access() {
+        let diags = diagnostics("#include \"/etc/passwd\"");
+        assert!(diags[0]["message"].as_str().unwrap().contains("Cannot find include file"));
+    }
+
+    #[test]
+    fn valid_programs_reach_execution() {
+        let diags = diagnostics("int main() { return 0; }");
+        assert_eq!(diags[0]["message"], json!("Error: Program execution is not supported yet"));
+        assert_eq!(diags[0]["span"], Value::Null);
+    }
+
+    #[test]
+    fn dialects() {
+        let source = "@inline int main() { return 0; }";
+        let strict = compile_and_run(source, None, None, None);
+        assert!(strict.diagnostics.contains("Syntax Error"));
+        let extended = compile_and_run(source, None, Some("extended".into()), None);
+        assert!(extended.diagnostics.contains("not supported yet"));
+        let unknown = compile_and_run(source, None, Some("loose".into()), None);
+        assert!(unknown.diagnostics.contains("unknown dialect 'loose'"));
+    }
+
+    #[test]
+    fn memory_modes() {
+        let source = "int main() { return 0; }";
+        let gc = compile_and_run(source, None, None, Some("gc".into()));
+        assert!(gc.diagnostics.contains("not supported yet"));
+        let unknown = compile_and_run(source, None, None, Some("arena".into()));
+        assert!(unknown.diagnostics.contains("unknown memory mode 'arena'"));
+    }
+}